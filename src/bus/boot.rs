@@ -0,0 +1,45 @@
+use std::{error::Error, fmt};
+
+/// DMG, MGB and SGB boot ROMs
+const DMG_SIZE: usize = 0x100;
+/// CGB boot ROMs skip over the cartridge header at $0100-$01FF
+const CGB_SIZE: usize = 0x900;
+
+const HEADER_START: u16 = 0x0100;
+const HEADER_END: u16 = 0x01FF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootRomError(pub usize);
+
+impl fmt::Display for BootRomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid boot ROM size: {} bytes, expected {} or {}",
+            self.0, DMG_SIZE, CGB_SIZE
+        )
+    }
+}
+
+impl Error for BootRomError {}
+
+pub struct BootRom {
+    data: Vec<u8>,
+}
+
+impl BootRom {
+    pub fn new(data: Vec<u8>) -> Result<Self, BootRomError> {
+        match data.len() {
+            DMG_SIZE | CGB_SIZE => Ok(Self { data }),
+            len => Err(BootRomError(len)),
+        }
+    }
+
+    /// Returns `None` where the cartridge shows through the boot ROM
+    pub fn read(&self, address: u16) -> Option<u8> {
+        match address {
+            HEADER_START..=HEADER_END => None,
+            _ => self.data.get(address as usize).copied(),
+        }
+    }
+}
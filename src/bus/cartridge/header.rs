@@ -1,10 +1,13 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, error::Error, fmt};
 
 use once_cell::sync::Lazy;
 
 pub const HEADER_LOC: usize = 0x0100;
 pub const HEADER_SIZE: usize = 0x50;
 
+const LOGO_LOC: usize = 0x04;
+const LOGO_END: usize = 0x34;
+
 const TITLE_LOC: usize = 0x34;
 const DMG_TITLE_SIZE: usize = 0x10;
 const CGB_TITLE_SIZE: usize = 0x0B;
@@ -28,89 +31,570 @@ const CHECKSUM_LOC: usize = 0x4D;
 const CHECKSUM_START: usize = 0x34;
 const CHECKSUM_END: usize = 0x4D;
 
+const GLOBAL_CHECKSUM_LOC: usize = 0x4E;
+
+/// Old licensee code telling the CGB boot ROM to hash the title
+const NINTENDO_LIC: u8 = 0x01;
+/// Old licensee code deferring to the new licensee code, required for SGB
+/// functions
+const NEW_LIC: u8 = 0x33;
+
+pub const NINTENDO_LOGO: [u8; LOGO_END - LOGO_LOC] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderError {
+    RomTooShort(usize),
+    RomTooLarge(usize),
+    UnknownCartridgeType(u8),
+    InvalidRomSize(u8),
+    InvalidRamSize(u8),
+    RomSizeMismatch { declared: usize, actual: usize },
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeaderError::RomTooShort(len) => write!(
+                f,
+                "ROM is {} bytes long, too short to contain a header (${:04X} bytes)",
+                len,
+                HEADER_LOC + HEADER_SIZE
+            ),
+            HeaderError::RomTooLarge(len) => write!(
+                f,
+                "ROM is {} bytes long, larger than any valid ROM size",
+                len
+            ),
+            HeaderError::UnknownCartridgeType(code) => {
+                write!(f, "Unknown cartridge type ${:02X}", code)
+            }
+            HeaderError::InvalidRomSize(code) => write!(f, "Invalid ROM size code ${:02X}", code),
+            HeaderError::InvalidRamSize(code) => write!(f, "Invalid RAM size code ${:02X}", code),
+            HeaderError::RomSizeMismatch { declared, actual } => write!(
+                f,
+                "ROM is {} bytes long but the header declares {} bytes",
+                actual, declared
+            ),
+        }
+    }
+}
+
+impl Error for HeaderError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CartridgeType {
+    RomOnly,
+    Mbc1,
+    Mbc1Ram,
+    Mbc1RamBattery,
+    Mbc2,
+    Mbc2Battery,
+    RomRam,
+    RomRamBattery,
+    Mmm01,
+    Mmm01Ram,
+    Mmm01RamBattery,
+    Mbc3TimerBattery,
+    Mbc3TimerRamBattery,
+    Mbc3,
+    Mbc3Ram,
+    Mbc3RamBattery,
+    Mbc5,
+    Mbc5Ram,
+    Mbc5RamBattery,
+    Mbc5Rumble,
+    Mbc5RumbleRam,
+    Mbc5RumbleRamBattery,
+    Mbc6,
+    Mbc7SensorRumbleRamBattery,
+    PocketCamera,
+    BandaiTama5,
+    HuC3,
+    HuC1RamBattery,
+}
+
+impl CartridgeType {
+    pub const fn from_code(code: u8) -> Option<Self> {
+        let t = match code {
+            0x00 => Self::RomOnly,
+            0x01 => Self::Mbc1,
+            0x02 => Self::Mbc1Ram,
+            0x03 => Self::Mbc1RamBattery,
+            0x05 => Self::Mbc2,
+            0x06 => Self::Mbc2Battery,
+            0x08 => Self::RomRam,
+            0x09 => Self::RomRamBattery,
+            0x0B => Self::Mmm01,
+            0x0C => Self::Mmm01Ram,
+            0x0D => Self::Mmm01RamBattery,
+            0x0F => Self::Mbc3TimerBattery,
+            0x10 => Self::Mbc3TimerRamBattery,
+            0x11 => Self::Mbc3,
+            0x12 => Self::Mbc3Ram,
+            0x13 => Self::Mbc3RamBattery,
+            0x19 => Self::Mbc5,
+            0x1A => Self::Mbc5Ram,
+            0x1B => Self::Mbc5RamBattery,
+            0x1C => Self::Mbc5Rumble,
+            0x1D => Self::Mbc5RumbleRam,
+            0x1E => Self::Mbc5RumbleRamBattery,
+            0x20 => Self::Mbc6,
+            0x22 => Self::Mbc7SensorRumbleRamBattery,
+            0xFC => Self::PocketCamera,
+            0xFD => Self::BandaiTama5,
+            0xFE => Self::HuC3,
+            0xFF => Self::HuC1RamBattery,
+            _ => return None,
+        };
+        Some(t)
+    }
+
+    pub const fn code(self) -> u8 {
+        match self {
+            Self::RomOnly => 0x00,
+            Self::Mbc1 => 0x01,
+            Self::Mbc1Ram => 0x02,
+            Self::Mbc1RamBattery => 0x03,
+            Self::Mbc2 => 0x05,
+            Self::Mbc2Battery => 0x06,
+            Self::RomRam => 0x08,
+            Self::RomRamBattery => 0x09,
+            Self::Mmm01 => 0x0B,
+            Self::Mmm01Ram => 0x0C,
+            Self::Mmm01RamBattery => 0x0D,
+            Self::Mbc3TimerBattery => 0x0F,
+            Self::Mbc3TimerRamBattery => 0x10,
+            Self::Mbc3 => 0x11,
+            Self::Mbc3Ram => 0x12,
+            Self::Mbc3RamBattery => 0x13,
+            Self::Mbc5 => 0x19,
+            Self::Mbc5Ram => 0x1A,
+            Self::Mbc5RamBattery => 0x1B,
+            Self::Mbc5Rumble => 0x1C,
+            Self::Mbc5RumbleRam => 0x1D,
+            Self::Mbc5RumbleRamBattery => 0x1E,
+            Self::Mbc6 => 0x20,
+            Self::Mbc7SensorRumbleRamBattery => 0x22,
+            Self::PocketCamera => 0xFC,
+            Self::BandaiTama5 => 0xFD,
+            Self::HuC3 => 0xFE,
+            Self::HuC1RamBattery => 0xFF,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::RomOnly => "ROM ONLY",
+            Self::Mbc1 => "MBC1",
+            Self::Mbc1Ram => "MBC1+RAM",
+            Self::Mbc1RamBattery => "MBC1+RAM+BATTERY",
+            Self::Mbc2 => "MBC2",
+            Self::Mbc2Battery => "MBC2+BATTERY",
+            Self::RomRam => "ROM+RAM",
+            Self::RomRamBattery => "ROM+RAM+BATTERY",
+            Self::Mmm01 => "MMM01",
+            Self::Mmm01Ram => "MMM01+RAM",
+            Self::Mmm01RamBattery => "MMM01+RAM+BATTERY",
+            Self::Mbc3TimerBattery => "MBC3+TIMER+BATTERY",
+            Self::Mbc3TimerRamBattery => "MBC3+TIMER+RAM+BATTERY",
+            Self::Mbc3 => "MBC3",
+            Self::Mbc3Ram => "MBC3+RAM",
+            Self::Mbc3RamBattery => "MBC3+RAM+BATTERY",
+            Self::Mbc5 => "MBC5",
+            Self::Mbc5Ram => "MBC5+RAM",
+            Self::Mbc5RamBattery => "MBC5+RAM+BATTERY",
+            Self::Mbc5Rumble => "MBC5+RUMBLE",
+            Self::Mbc5RumbleRam => "MBC5+RUMBLE+RAM",
+            Self::Mbc5RumbleRamBattery => "MBC5+RUMBLE+RAM+BATTERY",
+            Self::Mbc6 => "MBC6",
+            Self::Mbc7SensorRumbleRamBattery => "MBC7+SENSOR+RUMBLE+RAM+BATTERY",
+            Self::PocketCamera => "POCKET CAMERA",
+            Self::BandaiTama5 => "BANDAI TAMA5",
+            Self::HuC3 => "HuC3",
+            Self::HuC1RamBattery => "HuC1+RAM+BATTERY",
+        }
+    }
+
+    /// Name of the memory bank controller without the extra hardware
+    pub const fn mapper(self) -> &'static str {
+        match self {
+            Self::RomOnly | Self::RomRam | Self::RomRamBattery => "None",
+            Self::Mbc1 | Self::Mbc1Ram | Self::Mbc1RamBattery => "MBC1",
+            Self::Mbc2 | Self::Mbc2Battery => "MBC2",
+            Self::Mmm01 | Self::Mmm01Ram | Self::Mmm01RamBattery => "MMM01",
+            Self::Mbc3TimerBattery
+            | Self::Mbc3TimerRamBattery
+            | Self::Mbc3
+            | Self::Mbc3Ram
+            | Self::Mbc3RamBattery => "MBC3",
+            Self::Mbc5
+            | Self::Mbc5Ram
+            | Self::Mbc5RamBattery
+            | Self::Mbc5Rumble
+            | Self::Mbc5RumbleRam
+            | Self::Mbc5RumbleRamBattery => "MBC5",
+            Self::Mbc6 => "MBC6",
+            Self::Mbc7SensorRumbleRamBattery => "MBC7",
+            Self::PocketCamera => "POCKET CAMERA",
+            Self::BandaiTama5 => "TAMA5",
+            Self::HuC3 => "HuC3",
+            Self::HuC1RamBattery => "HuC1",
+        }
+    }
+
+    pub const fn has_battery(self) -> bool {
+        matches!(
+            self,
+            Self::Mbc1RamBattery
+                | Self::Mbc2Battery
+                | Self::RomRamBattery
+                | Self::Mmm01RamBattery
+                | Self::Mbc3TimerBattery
+                | Self::Mbc3TimerRamBattery
+                | Self::Mbc3RamBattery
+                | Self::Mbc5RamBattery
+                | Self::Mbc5RumbleRamBattery
+                | Self::Mbc7SensorRumbleRamBattery
+                | Self::HuC3
+                | Self::HuC1RamBattery
+        )
+    }
+
+    /// Whether the cartridge keeps data across power cycles, in battery-backed
+    /// RAM or in flash
+    pub const fn has_save(self) -> bool {
+        self.has_battery() || matches!(self, Self::Mbc6)
+    }
+
+    pub const fn has_rtc(self) -> bool {
+        matches!(
+            self,
+            Self::Mbc3TimerBattery | Self::Mbc3TimerRamBattery | Self::HuC3
+        )
+    }
+
+    pub const fn has_rumble(self) -> bool {
+        matches!(
+            self,
+            Self::Mbc5Rumble | Self::Mbc5RumbleRam | Self::Mbc5RumbleRamBattery
+        )
+    }
+}
+
+impl fmt::Display for CartridgeType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CgbSupport {
+    None,
+    Enhanced,
+    Only,
+}
+
+impl CgbSupport {
+    pub const fn from_code(code: u8) -> Self {
+        // 0b1000_0000 0b1100_0000
+        match code & 0xF0 {
+            0xC0 => Self::Only,
+            0x80 => Self::Enhanced,
+            _ => Self::None,
+        }
+    }
+}
+
+impl fmt::Display for CgbSupport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Self::None => "DMG",
+            Self::Enhanced => "CGB enhanced",
+            Self::Only => "CGB only",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SgbSupport {
+    None,
+    Supported,
+}
+
+impl SgbSupport {
+    /// The SGB flag is only honoured when the old licensee code is $33
+    pub const fn from_code(code: u8, old_licensee: u8) -> Self {
+        match (code, old_licensee) {
+            (0x03, NEW_LIC) => Self::Supported,
+            _ => Self::None,
+        }
+    }
+}
+
+impl fmt::Display for SgbSupport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Self::None => "No",
+            Self::Supported => "Yes",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RomSize {
+    Kb32,
+    Kb64,
+    Kb128,
+    Kb256,
+    Kb512,
+    Mb1,
+    Mb2,
+    Mb4,
+    Mb8,
+    Kb1152,
+    Kb1280,
+    Kb1536,
+}
+
+impl RomSize {
+    pub const fn from_code(code: u8) -> Option<Self> {
+        let size = match code {
+            0x00 => Self::Kb32,
+            0x01 => Self::Kb64,
+            0x02 => Self::Kb128,
+            0x03 => Self::Kb256,
+            0x04 => Self::Kb512,
+            0x05 => Self::Mb1,
+            0x06 => Self::Mb2,
+            0x07 => Self::Mb4,
+            0x08 => Self::Mb8,
+            0x52 => Self::Kb1152,
+            0x53 => Self::Kb1280,
+            0x54 => Self::Kb1536,
+            _ => return None,
+        };
+        Some(size)
+    }
+
+    pub const fn code(self) -> u8 {
+        match self {
+            Self::Kb32 => 0x00,
+            Self::Kb64 => 0x01,
+            Self::Kb128 => 0x02,
+            Self::Kb256 => 0x03,
+            Self::Kb512 => 0x04,
+            Self::Mb1 => 0x05,
+            Self::Mb2 => 0x06,
+            Self::Mb4 => 0x07,
+            Self::Mb8 => 0x08,
+            Self::Kb1152 => 0x52,
+            Self::Kb1280 => 0x53,
+            Self::Kb1536 => 0x54,
+        }
+    }
+
+    pub const fn banks(self) -> usize {
+        match self {
+            Self::Kb1152 => 72,
+            Self::Kb1280 => 80,
+            Self::Kb1536 => 96,
+            _ => 2 << self.code(),
+        }
+    }
+
+    pub const fn bytes(self) -> usize {
+        self.banks() * 0x4000
+    }
+}
+
+impl fmt::Display for RomSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_size(f, self.bytes())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RamSize {
+    None,
+    Kb2,
+    Kb8,
+    Kb32,
+    Kb128,
+    Kb64,
+}
+
+impl RamSize {
+    pub const fn from_code(code: u8) -> Option<Self> {
+        let size = match code {
+            0x00 => Self::None,
+            0x01 => Self::Kb2,
+            0x02 => Self::Kb8,
+            0x03 => Self::Kb32,
+            0x04 => Self::Kb128,
+            0x05 => Self::Kb64,
+            _ => return None,
+        };
+        Some(size)
+    }
+
+    pub const fn bytes(self) -> usize {
+        match self {
+            Self::None => 0,
+            Self::Kb2 => 0x800,
+            Self::Kb8 => 0x2000,
+            Self::Kb32 => 0x8000,
+            Self::Kb128 => 0x2_0000,
+            Self::Kb64 => 0x1_0000,
+        }
+    }
+
+    pub const fn banks(self) -> usize {
+        self.bytes().div_ceil(0x2000)
+    }
+}
+
+impl fmt::Display for RamSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "No RAM"),
+            _ => fmt_size(f, self.bytes()),
+        }
+    }
+}
+
+fn fmt_size(f: &mut fmt::Formatter, bytes: usize) -> fmt::Result {
+    let kb = bytes / 1024;
+    match kb >= 1024 && kb.is_multiple_of(1024) {
+        true => write!(f, "{} MBs", kb / 1024),
+        false => write!(f, "{} KBs", kb),
+    }
+}
+
 pub struct Header {
     pub title: String,
     pub licensee: &'static str,
-    pub cgb_flag: u8,
-    pub sgb_flag: u8,
-    pub cart_type: &'static str,
-    pub rom_size: u32,
-    pub n_banks: u32,
-    pub ram_size: u32,
+    pub cgb: CgbSupport,
+    pub sgb: SgbSupport,
+    pub cart_type: CartridgeType,
+    pub rom_size: RomSize,
+    pub ram_size: RamSize,
     pub dst: &'static str,
     pub version: u8,
+    pub stored_checksum: u8,
+    pub stored_global_checksum: u16,
     pub checksum: bool,
+    pub global_checksum: bool,
+    pub logo: bool,
+    /// Sum of the 16 title bytes, as computed by the CGB boot ROM for
+    /// Nintendo-licensed DMG games, 0 otherwise
+    pub title_hash: u8,
 }
 
 impl Header {
-    pub fn new(buf: [u8; HEADER_SIZE]) -> Self {
-        let title_end = TITLE_LOC + title_size(buf[CGB_FLAG_LOC]);
+    /// Parses the header of `rom` and checks that the ROM is at least as long
+    /// as the size it declares
+    pub fn parse(rom: &[u8]) -> Result<Self, HeaderError> {
+        Self::parse_at(rom, 0)
+    }
+
+    /// Parses the header of the bank starting `base` bytes into `rom`, for
+    /// multicarts whose own header is not in the first bank
+    pub fn parse_at(rom: &[u8], base: usize) -> Result<Self, HeaderError> {
+        let bank = rom.get(base..).unwrap_or_default();
+        let buf = bank
+            .get(HEADER_LOC..HEADER_LOC + HEADER_SIZE)
+            .ok_or(HeaderError::RomTooShort(rom.len()))?;
+
+        let cart_type = CartridgeType::from_code(buf[CART_TYPE_LOC])
+            .ok_or(HeaderError::UnknownCartridgeType(buf[CART_TYPE_LOC]))?;
+        let rom_size = RomSize::from_code(buf[ROM_SIZE_LOC])
+            .ok_or(HeaderError::InvalidRomSize(buf[ROM_SIZE_LOC]))?;
+        let ram_size = RamSize::from_code(buf[RAM_SIZE_LOC])
+            .ok_or(HeaderError::InvalidRamSize(buf[RAM_SIZE_LOC]))?;
+
+        if rom.len() < rom_size.bytes() {
+            return Err(HeaderError::RomSizeMismatch {
+                declared: rom_size.bytes(),
+                actual: rom.len(),
+            });
+        }
+
+        let cgb = CgbSupport::from_code(buf[CGB_FLAG_LOC]);
+        let title_end = TITLE_LOC + title_size(cgb);
         let slice = &buf[TITLE_LOC..title_end];
-        let title = String::from_utf8_lossy(slice).to_string();
+        let title = String::from_utf8_lossy(slice)
+            .trim_end_matches(char::from(0))
+            .to_string();
 
-        let mut chksum_bytes = [0; CHECKSUM_END - CHECKSUM_START];
-        chksum_bytes.copy_from_slice(&buf[CHECKSUM_START..CHECKSUM_END]);
+        let (stored_checksum, global_sum) = stored_checksums(bank);
 
-        Self {
+        Ok(Self {
             title,
             licensee: licensee(buf[OLD_LIC_LOC], [buf[NEW_LIC_LOC], buf[NEW_LIC_LOC + 1]]),
-            cart_type: cart_type(buf[CART_TYPE_LOC]),
-            cgb_flag: buf[CGB_FLAG_LOC],
-            sgb_flag: buf[SGB_FLAG_LOC],
-            rom_size: 32 << buf[ROM_SIZE_LOC],
-            n_banks: 2 << buf[ROM_SIZE_LOC],
-            ram_size: ram_size(buf[RAM_SIZE_LOC]),
+            cgb,
+            sgb: SgbSupport::from_code(buf[SGB_FLAG_LOC], buf[OLD_LIC_LOC]),
+            cart_type,
+            rom_size,
+            ram_size,
             dst: destination(buf[DST_LOC]),
             version: buf[VERSION_LOC],
-            checksum: checksum(buf[CHECKSUM_LOC], chksum_bytes),
-        }
+            stored_checksum,
+            stored_global_checksum: global_sum,
+            checksum: header_checksum(bank) == stored_checksum,
+            global_checksum: global_checksum(rom) == global_sum,
+            logo: has_logo(bank),
+            title_hash: title_hash(buf),
+        })
     }
 
     pub fn print(&self, width: usize) {
         println!("{:<width$} {}", "Title:", self.title, width = width);
         println!("{:<width$} {}", "Licensee:", self.licensee, width = width);
         println!("{:<width$} {}", "Type:", self.cart_type, width = width);
-
-        let (rom_size, unit) = match self.rom_size > 512 {
-            true => (format!("{}", self.rom_size / 1024), "MBs"),
-            false => (format!("{}", self.rom_size), "KBs"),
-        };
-        println!(
-            "{:<width$} {} {}",
-            "ROM Size:",
-            rom_size,
-            unit,
-            width = width
-        );
-        println!("{:<width$} {}", "ROM Banks:", self.n_banks, width = width);
-
-        let (ram_size, unit) = match self.ram_size {
-            0 => ("No RAM".to_string(), ""),
-            _ => (format!("{}", self.ram_size), "KBs"),
-        };
+        println!("{:<width$} {}", "CGB:", self.cgb, width = width);
+        println!("{:<width$} {}", "SGB:", self.sgb, width = width);
+        println!("{:<width$} {}", "ROM Size:", self.rom_size, width = width);
         println!(
-            "{:<width$} {} {}",
-            "RAM Size:",
-            ram_size,
-            unit,
+            "{:<width$} {}",
+            "ROM Banks:",
+            self.rom_size.banks(),
             width = width
         );
-        if self.ram_size > 0 {
+        println!("{:<width$} {}", "RAM Size:", self.ram_size, width = width);
+        if self.ram_size != RamSize::None {
             println!(
                 "{:<width$} {}",
                 "RAM Banks:",
-                self.ram_size / 8,
+                self.ram_size.banks(),
                 width = width
             );
         }
         println!("{:<width$} {}", "Region:", self.dst, width = width);
         println!("{:<width$} {}", "Version:", self.version, width = width);
 
-        let result = match self.checksum {
-            true => "Ok",
-            false => "Failed",
-        };
-        println!("{:<width$} {}", "Checksum:", result, width = width);
+        println!(
+            "{:<width$} {}",
+            "Checksum:",
+            result(self.checksum),
+            width = width
+        );
+        println!(
+            "{:<width$} {}",
+            "Global Sum:",
+            result(self.global_checksum),
+            width = width
+        );
+        println!("{:<width$} {}", "Logo:", result(self.logo), width = width);
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.checksum && self.global_checksum && self.logo
     }
 }
 
@@ -118,31 +602,29 @@ fn licensee(old: u8, new: [u8; 2]) -> &'static str {
     let hi = new[0] & 0x0F;
     let lo = new[1] & 0x0F;
     let new_lic_code = hi << 4 | lo;
-    match old == 0x33 {
+    match old == NEW_LIC {
         true => NEW_LICENSEES.get(&new_lic_code).unwrap_or(&"None"),
         false => OLD_LICENSEES.get(&old).unwrap_or(&"None"),
     }
 }
 
-fn cart_type(code: u8) -> &'static str {
-    CART_TYPES.get(&code).unwrap_or(&"ROM ONLY")
-}
-
-const fn title_size(code: u8) -> usize {
-    // 0b1000_0000 0b1100_0000
-    match code & 0xF0 == 0x80 || code & 0xF0 == 0xC0 {
-        true => CGB_TITLE_SIZE,
-        false => DMG_TITLE_SIZE,
+fn title_hash(buf: &[u8]) -> u8 {
+    let nintendo = match buf[OLD_LIC_LOC] {
+        NEW_LIC => buf[NEW_LIC_LOC..NEW_LIC_LOC + 2] == *b"01",
+        code => code == NINTENDO_LIC,
+    };
+    match nintendo {
+        true => buf[TITLE_LOC..TITLE_LOC + DMG_TITLE_SIZE]
+            .iter()
+            .fold(0u8, |acc, &v| acc.wrapping_add(v)),
+        false => 0,
     }
 }
 
-const fn ram_size(code: u8) -> u32 {
-    match code {
-        0x02 => 8,
-        0x03 => 32,
-        0x04 => 128,
-        0x05 => 64,
-        _ => 0,
+const fn title_size(cgb: CgbSupport) -> usize {
+    match cgb {
+        CgbSupport::Enhanced | CgbSupport::Only => CGB_TITLE_SIZE,
+        CgbSupport::None => DMG_TITLE_SIZE,
     }
 }
 
@@ -153,47 +635,71 @@ const fn destination(code: u8) -> &'static str {
     }
 }
 
-fn checksum(sum: u8, bytes: [u8; CHECKSUM_END - CHECKSUM_START]) -> bool {
-    let x = bytes
-        .iter()
-        .fold(0u8, |acc, &v| acc.wrapping_sub(v).wrapping_sub(1));
+pub(crate) const fn result(ok: bool) -> &'static str {
+    match ok {
+        true => "Ok",
+        false => "Failed",
+    }
+}
+
+/// Byte used to fill the ROM when padding it to a valid size
+const PAD_BYTE: u8 = 0xFF;
+
+/// Inserts the Nintendo logo and recomputes both checksums. With `pad`, the
+/// ROM is first padded to the next valid size and the ROM size byte updated.
+pub fn fix_header(rom: &mut Vec<u8>, pad: bool) -> Result<(), HeaderError> {
+    if pad {
+        // Look the size up first so a ROM that is too large is left untouched
+        let size = rom.len().max(RomSize::Kb32.bytes()).next_power_of_two();
+        let code = (0x00..=0x08)
+            .filter_map(RomSize::from_code)
+            .find(|s| s.bytes() == size)
+            .ok_or(HeaderError::RomTooLarge(rom.len()))?
+            .code();
+        rom.resize(size, PAD_BYTE);
+        rom[HEADER_LOC + ROM_SIZE_LOC] = code;
+    }
 
-    x == sum
+    if rom.len() < HEADER_LOC + HEADER_SIZE {
+        return Err(HeaderError::RomTooShort(rom.len()));
+    }
+
+    rom[HEADER_LOC + LOGO_LOC..HEADER_LOC + LOGO_END].copy_from_slice(&NINTENDO_LOGO);
+    rom[HEADER_LOC + CHECKSUM_LOC] = header_checksum(rom);
+    let sum = global_checksum(rom);
+    rom[HEADER_LOC + GLOBAL_CHECKSUM_LOC..HEADER_LOC + GLOBAL_CHECKSUM_LOC + 2]
+        .copy_from_slice(&sum.to_be_bytes());
+
+    Ok(())
 }
 
-static CART_TYPES: Lazy<HashMap<u8, &'static str>> = Lazy::new(|| {
-    let mut m = HashMap::new();
+/// Header checksum over $0134-$014C, `rom` must contain the full header
+pub fn header_checksum(rom: &[u8]) -> u8 {
+    rom[HEADER_LOC + CHECKSUM_START..HEADER_LOC + CHECKSUM_END]
+        .iter()
+        .fold(0u8, |acc, &v| acc.wrapping_sub(v).wrapping_sub(1))
+}
 
-    m.insert(0x01, "MBC1");
-    m.insert(0x02, "MBC1+RAM");
-    m.insert(0x03, "MBC1+RAM+BATTERY");
-    m.insert(0x05, "MBC2");
-    m.insert(0x06, "MBC2+BATTERY");
-    m.insert(0x08, "ROM+RAM");
-    m.insert(0x09, "ROM+RAM+BATTERY");
-    m.insert(0x0B, "MMM01");
-    m.insert(0x0C, "MMM01+RAM");
-    m.insert(0x0D, "MMM01+RAM+BATTERY");
-    m.insert(0x0F, "MBC3+TIMER+BATTERY");
-    m.insert(0x10, "MBC3+TIMER+RAM+BATTERY");
-    m.insert(0x11, "MBC3");
-    m.insert(0x12, "MBC3+RAM");
-    m.insert(0x13, "MBC3+RAM+BATTERY");
-    m.insert(0x19, "MBC5");
-    m.insert(0x1A, "MBC5+RAM");
-    m.insert(0x1B, "MBC5+RAM+BATTERY");
-    m.insert(0x1C, "MBC5+RUMBLE");
-    m.insert(0x1D, "MBC5+RUMBLE+RAM");
-    m.insert(0x1E, "MBC5+RUMBLE+RAM+BATTERY");
-    m.insert(0x20, "MBC6");
-    m.insert(0x22, "MBC7+SENSOR+RUMBLE+RAM+BATTERY");
-    m.insert(0xFC, "POCKET CAMERA");
-    m.insert(0xFD, "BANDAI TAMA5");
-    m.insert(0xFE, "HuC3");
-    m.insert(0xFF, "HuC1+RAM+BATTERY");
+/// Header and global checksums stored at $014D-$014F, `rom` must contain the full header
+pub fn stored_checksums(rom: &[u8]) -> (u8, u16) {
+    let buf = &rom[HEADER_LOC..];
+    let global = u16::from_be_bytes([buf[GLOBAL_CHECKSUM_LOC], buf[GLOBAL_CHECKSUM_LOC + 1]]);
+    (buf[CHECKSUM_LOC], global)
+}
 
-    m
-});
+/// Whether the Nintendo logo at $0104-$0133 is intact, `rom` must contain the full header
+pub fn has_logo(rom: &[u8]) -> bool {
+    rom[HEADER_LOC + LOGO_LOC..HEADER_LOC + LOGO_END] == NINTENDO_LOGO
+}
+
+/// Sum of every ROM byte except the global checksum itself at $014E-$014F
+pub fn global_checksum(rom: &[u8]) -> u16 {
+    let loc = HEADER_LOC + GLOBAL_CHECKSUM_LOC;
+    rom.iter()
+        .enumerate()
+        .filter(|&(i, _)| i != loc && i != loc + 1)
+        .fold(0u16, |acc, (_, &v)| acc.wrapping_add(v as u16))
+}
 
 static NEW_LICENSEES: Lazy<HashMap<u8, &'static str>> = Lazy::new(|| {
     let mut m = HashMap::new();
@@ -0,0 +1,72 @@
+use super::{ram_offset, rom_offset, Mbc};
+
+const IR_MODE: u8 = 0x0E;
+
+/// Value read from the IR port when no light is received
+const IR_NO_LIGHT: u8 = 0xC0;
+
+pub struct HuC1 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ir_mode: bool,
+    rom_bank: u8,
+    ram_bank: u8,
+}
+
+impl HuC1 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        Self {
+            rom,
+            ram: vec![0; ram_size],
+            ir_mode: false,
+            rom_bank: 1,
+            ram_bank: 0,
+        }
+    }
+}
+
+impl Mbc for HuC1 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank as usize,
+        };
+        let offset = rom_offset(&self.rom, bank, address);
+        self.rom.get(offset).copied().unwrap_or(0xFF)
+    }
+
+    fn write_rom(&mut self, address: u16, data: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ir_mode = data == IR_MODE,
+            0x2000..=0x3FFF => self.rom_bank = data & 0x3F,
+            0x4000..=0x5FFF => self.ram_bank = data & 0x03,
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        match (self.ir_mode, self.ram.is_empty()) {
+            (true, _) => IR_NO_LIGHT,
+            (false, true) => 0xFF,
+            (false, false) => self.ram[ram_offset(&self.ram, self.ram_bank as usize, address)],
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, data: u8) -> bool {
+        // No IR peer is emulated, the LED output is ignored
+        if self.ir_mode || self.ram.is_empty() {
+            return false;
+        }
+
+        let offset = ram_offset(&self.ram, self.ram_bank as usize, address);
+        std::mem::replace(&mut self.ram[offset], data) != data
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+}
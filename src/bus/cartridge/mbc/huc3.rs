@@ -0,0 +1,233 @@
+use super::{ram_offset, rom_offset, rtc::now, Mbc};
+
+const MODE_RAM_READ: u8 = 0x00;
+const MODE_RAM: u8 = 0x0A;
+const MODE_RTC_COMMAND: u8 = 0x0B;
+const MODE_RTC_RESPONSE: u8 = 0x0C;
+const MODE_RTC_SEMAPHORE: u8 = 0x0D;
+const MODE_IR: u8 = 0x0E;
+
+const IR_NO_LIGHT: u8 = 0xC0;
+
+const CMD_READ: u8 = 0x1;
+const CMD_WRITE: u8 = 0x3;
+const CMD_INDEX_LO: u8 = 0x4;
+const CMD_INDEX_HI: u8 = 0x5;
+const CMD_EXTENDED: u8 = 0x6;
+
+const EXT_LATCH: u8 = 0x0;
+const EXT_SET: u8 = 0x1;
+const EXT_STATUS: u8 = 0x2;
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// SameBoy footer: 64-bit UNIX timestamp, then minutes, days, alarm minutes
+/// and alarm days as little-endian u16 and an alarm enable byte
+const FOOTER_SIZE: usize = 17;
+
+pub struct HuC3 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    mode: u8,
+    rom_bank: u8,
+    ram_bank: u8,
+    command: u8,
+    response: u8,
+    index: u8,
+    registers: [u8; 0x100],
+    clock: Clock,
+}
+
+/// Time of day in minutes and a 12-bit day counter, advanced from the host clock.
+/// The alarm is not emulated, it is only kept so saves round-trip.
+struct Clock {
+    minutes: u32,
+    days: u32,
+    timestamp: u64,
+    alarm_minutes: u16,
+    alarm_days: u16,
+    alarm_enabled: bool,
+}
+
+impl Clock {
+    fn new() -> Self {
+        Self {
+            minutes: 0,
+            days: 0,
+            timestamp: now(),
+            alarm_minutes: 0,
+            alarm_days: 0,
+            alarm_enabled: false,
+        }
+    }
+
+    fn update(&mut self) {
+        let elapsed = now().saturating_sub(self.timestamp) / 60;
+        self.timestamp += elapsed * 60;
+
+        let minutes = self.minutes as u64 + elapsed;
+        self.minutes = (minutes % MINUTES_PER_DAY as u64) as u32;
+        self.days = ((self.days as u64 + minutes / MINUTES_PER_DAY as u64) & 0xFFF) as u32;
+    }
+
+    fn footer(&self) -> [u8; FOOTER_SIZE] {
+        let mut footer = [0; FOOTER_SIZE];
+        footer[0..8].copy_from_slice(&self.timestamp.to_le_bytes());
+        footer[8..10].copy_from_slice(&(self.minutes as u16).to_le_bytes());
+        footer[10..12].copy_from_slice(&(self.days as u16).to_le_bytes());
+        footer[12..14].copy_from_slice(&self.alarm_minutes.to_le_bytes());
+        footer[14..16].copy_from_slice(&self.alarm_days.to_le_bytes());
+        footer[16] = self.alarm_enabled as u8;
+        footer
+    }
+
+    /// Restores the clock and catches up by the real time elapsed since the save
+    fn load_footer(&mut self, data: &[u8]) {
+        if data.len() != FOOTER_SIZE {
+            return;
+        }
+
+        let word = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]);
+        let mut timestamp = [0; 8];
+        timestamp.copy_from_slice(&data[0..8]);
+
+        self.timestamp = u64::from_le_bytes(timestamp);
+        self.minutes = word(8) as u32 % MINUTES_PER_DAY;
+        self.days = word(10) as u32 & 0xFFF;
+        self.alarm_minutes = word(12);
+        self.alarm_days = word(14);
+        self.alarm_enabled = data[16] & 0x01 != 0;
+        self.update();
+    }
+}
+
+impl HuC3 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        Self {
+            rom,
+            ram: vec![0; ram_size],
+            mode: MODE_RAM_READ,
+            rom_bank: 1,
+            ram_bank: 0,
+            command: 0,
+            response: 0,
+            index: 0,
+            registers: [0; 0x100],
+            clock: Clock::new(),
+        }
+    }
+
+    /// Returns whether the clock was set
+    fn execute(&mut self) -> bool {
+        let arg = self.command & 0x0F;
+        let mut result = 0;
+        match self.command >> 4 {
+            CMD_READ => {
+                result = self.registers[self.index as usize] & 0x0F;
+                self.index = self.index.wrapping_add(1);
+            }
+            CMD_WRITE => {
+                self.registers[self.index as usize] = arg;
+                self.index = self.index.wrapping_add(1);
+            }
+            CMD_INDEX_LO => self.index = (self.index & 0xF0) | arg,
+            CMD_INDEX_HI => self.index = (self.index & 0x0F) | (arg << 4),
+            CMD_EXTENDED => match arg {
+                EXT_LATCH => self.latch(),
+                EXT_SET => self.set_clock(),
+                EXT_STATUS => result = 0x1,
+                _ => {}
+            },
+            _ => {}
+        }
+        self.response = 0x80 | (self.command & 0x70) | result;
+        self.command >> 4 == CMD_EXTENDED && arg == EXT_SET
+    }
+
+    /// Copies the current time into registers $00-$05 as nibbles
+    fn latch(&mut self) {
+        self.clock.update();
+        for i in 0..3 {
+            self.registers[i] = ((self.clock.minutes >> (i * 4)) & 0x0F) as u8;
+            self.registers[i + 3] = ((self.clock.days >> (i * 4)) & 0x0F) as u8;
+        }
+    }
+
+    /// Loads the clock from registers $00-$05
+    fn set_clock(&mut self) {
+        let (mut minutes, mut days) = (0, 0);
+        for i in 0..3 {
+            minutes |= (self.registers[i] as u32 & 0x0F) << (i * 4);
+            days |= (self.registers[i + 3] as u32 & 0x0F) << (i * 4);
+        }
+        self.clock.minutes = minutes % MINUTES_PER_DAY;
+        self.clock.days = days;
+        self.clock.timestamp = now();
+    }
+}
+
+impl Mbc for HuC3 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank as usize,
+        };
+        let offset = rom_offset(&self.rom, bank, address);
+        self.rom.get(offset).copied().unwrap_or(0xFF)
+    }
+
+    fn write_rom(&mut self, address: u16, data: u8) {
+        match address {
+            0x0000..=0x1FFF => self.mode = data & 0x0F,
+            0x2000..=0x3FFF => self.rom_bank = data & 0x7F,
+            0x4000..=0x5FFF => self.ram_bank = data & 0x03,
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        match self.mode {
+            MODE_RAM_READ | MODE_RAM if !self.ram.is_empty() => {
+                self.ram[ram_offset(&self.ram, self.ram_bank as usize, address)]
+            }
+            MODE_RTC_RESPONSE => self.response,
+            // The clock is always ready
+            MODE_RTC_SEMAPHORE => 0x01,
+            MODE_IR => IR_NO_LIGHT,
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, data: u8) -> bool {
+        match self.mode {
+            MODE_RAM if !self.ram.is_empty() => {
+                let offset = ram_offset(&self.ram, self.ram_bank as usize, address);
+                std::mem::replace(&mut self.ram[offset], data) != data
+            }
+            MODE_RTC_COMMAND => {
+                self.command = data & 0x7F;
+                false
+            }
+            MODE_RTC_SEMAPHORE if data & 0x01 == 0 => self.execute(),
+            _ => false,
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        [self.ram.as_slice(), &self.clock.footer()].concat()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        let (ram, footer) = data.split_at(self.ram.len().min(data.len()));
+        self.ram[..ram.len()].copy_from_slice(ram);
+        self.clock.load_footer(footer);
+    }
+}
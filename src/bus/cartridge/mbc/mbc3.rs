@@ -0,0 +1,108 @@
+use super::{ram_offset, rom_offset, rtc::Rtc, Mbc};
+
+pub struct Mbc3 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    rtc: Option<Rtc>,
+    ram_enable: bool,
+    rom_bank: u8,
+    ram_bank: u8,
+    latch: u8,
+}
+
+impl Mbc3 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, has_rtc: bool) -> Self {
+        Self {
+            rom,
+            ram: vec![0; ram_size],
+            rtc: has_rtc.then(Rtc::new),
+            ram_enable: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            latch: 0xFF,
+        }
+    }
+}
+
+impl Mbc for Mbc3 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank as usize,
+        };
+        let offset = rom_offset(&self.rom, bank, address);
+        self.rom.get(offset).copied().unwrap_or(0xFF)
+    }
+
+    fn write_rom(&mut self, address: u16, data: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enable = data & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = (data & 0x7F).max(1),
+            0x4000..=0x5FFF => self.ram_bank = data & 0x0F,
+            0x6000..=0x7FFF => {
+                if let Some(rtc) = &mut self.rtc {
+                    if self.latch == 0x00 && data == 0x01 {
+                        rtc.latch();
+                    }
+                }
+                self.latch = data;
+            }
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enable {
+            return 0xFF;
+        }
+
+        match (self.ram_bank, &self.rtc) {
+            (0x00..=0x07, _) if !self.ram.is_empty() => {
+                self.ram[ram_offset(&self.ram, self.ram_bank as usize, address)]
+            }
+            (0x08..=0x0C, Some(rtc)) => rtc.read(self.ram_bank),
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, data: u8) -> bool {
+        if !self.ram_enable {
+            return false;
+        }
+
+        match (self.ram_bank, &mut self.rtc) {
+            (0x00..=0x07, _) if !self.ram.is_empty() => {
+                let offset = ram_offset(&self.ram, self.ram_bank as usize, address);
+                std::mem::replace(&mut self.ram[offset], data) != data
+            }
+            (0x08..=0x0C, Some(rtc)) => {
+                rtc.write(self.ram_bank, data);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        match &self.rtc {
+            Some(rtc) => [self.ram.as_slice(), &rtc.footer()].concat(),
+            None => self.ram.clone(),
+        }
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        let (ram, footer) = data.split_at(self.ram.len().min(data.len()));
+        self.ram[..ram.len()].copy_from_slice(ram);
+        if let Some(rtc) = &mut self.rtc {
+            rtc.load_footer(footer);
+        }
+    }
+}
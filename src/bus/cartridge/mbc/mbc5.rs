@@ -0,0 +1,82 @@
+use super::{ram_offset, rom_offset, Mbc};
+
+const RUMBLE_MOTOR: u8 = 1 << 3;
+
+pub struct Mbc5 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enable: bool,
+    rom_bank: u16,
+    ram_bank: u8,
+    has_rumble: bool,
+    motor: bool,
+}
+
+impl Mbc5 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, has_rumble: bool) -> Self {
+        Self {
+            rom,
+            ram: vec![0; ram_size],
+            ram_enable: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            has_rumble,
+            motor: false,
+        }
+    }
+}
+
+impl Mbc for Mbc5 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank as usize,
+        };
+        let offset = rom_offset(&self.rom, bank, address);
+        self.rom.get(offset).copied().unwrap_or(0xFF)
+    }
+
+    fn write_rom(&mut self, address: u16, data: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enable = data & 0x0F == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | data as u16,
+            0x3000..=0x3FFF => self.rom_bank = (self.rom_bank & 0xFF) | ((data as u16 & 1) << 8),
+            0x4000..=0x5FFF => match self.has_rumble {
+                true => {
+                    self.motor = data & RUMBLE_MOTOR != 0;
+                    self.ram_bank = data & 0x07;
+                }
+                false => self.ram_bank = data & 0x0F,
+            },
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        match self.ram_enable && !self.ram.is_empty() {
+            true => self.ram[ram_offset(&self.ram, self.ram_bank as usize, address)],
+            false => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, data: u8) -> bool {
+        if !self.ram_enable || self.ram.is_empty() {
+            return false;
+        }
+
+        let offset = ram_offset(&self.ram, self.ram_bank as usize, address);
+        std::mem::replace(&mut self.ram[offset], data) != data
+    }
+
+    fn rumble(&self) -> bool {
+        self.motor
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+}
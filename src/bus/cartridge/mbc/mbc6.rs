@@ -0,0 +1,214 @@
+use super::Mbc;
+
+const HALF_BANK_SIZE: usize = 0x2000;
+const RAM_HALF_BANK_SIZE: usize = 0x1000;
+
+const FLASH_SIZE: usize = 0x10_0000;
+const FLASH_SECTOR_SIZE: usize = 0x2_0000;
+const FLASH_SELECT: u8 = 0x08;
+
+const FLASH_MANUFACTURER: u8 = 0xC2;
+const FLASH_DEVICE: u8 = 0x81;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FlashState {
+    Ready,
+    Unlock1,
+    Unlock2,
+    Program,
+    Erase,
+    EraseUnlock1,
+    EraseUnlock2,
+}
+
+/// Two independent 8 KiB ROM/flash windows at $4000 and $6000, and two
+/// 4 KiB RAM windows at $A000 and $B000
+pub struct Mbc6 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    flash: Vec<u8>,
+    ram_enable: bool,
+    ram_banks: [u8; 2],
+    rom_banks: [u8; 2],
+    flash_select: [bool; 2],
+    flash_enable: bool,
+    flash_write_enable: bool,
+    flash_state: FlashState,
+    flash_id: bool,
+    flash_dirty: bool,
+}
+
+impl Mbc6 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        Self {
+            rom,
+            ram: vec![0; ram_size],
+            flash: vec![0xFF; FLASH_SIZE],
+            ram_enable: false,
+            ram_banks: [0; 2],
+            rom_banks: [0; 2],
+            flash_select: [false; 2],
+            flash_enable: false,
+            flash_write_enable: false,
+            flash_state: FlashState::Ready,
+            flash_id: false,
+            flash_dirty: false,
+        }
+    }
+
+    fn window(address: u16) -> usize {
+        (address as usize >> 13) & 1
+    }
+
+    fn offset(bank: u8, address: u16, size: usize, len: usize) -> usize {
+        (bank as usize * size + (address as usize & (size - 1))) % len.max(1)
+    }
+
+    fn ram_offset(&self, address: u16) -> usize {
+        let window = (address as usize >> 12) & 1;
+        Self::offset(
+            self.ram_banks[window],
+            address,
+            RAM_HALF_BANK_SIZE,
+            self.ram.len(),
+        )
+    }
+
+    fn read_flash(&self, offset: usize) -> u8 {
+        match self.flash_id {
+            true => match offset & 0x01 {
+                0 => FLASH_MANUFACTURER,
+                _ => FLASH_DEVICE,
+            },
+            false => self.flash[offset],
+        }
+    }
+
+    fn write_flash(&mut self, offset: usize, data: u8) {
+        if !self.flash_enable || !self.flash_write_enable {
+            return;
+        }
+
+        let command = offset & 0x7FFF;
+        self.flash_state = match (self.flash_state, command, data) {
+            (_, _, 0xF0) => {
+                self.flash_id = false;
+                FlashState::Ready
+            }
+            (FlashState::Ready, 0x5555, 0xAA) => FlashState::Unlock1,
+            (FlashState::Unlock1, 0x2AAA, 0x55) => FlashState::Unlock2,
+            (FlashState::Unlock2, 0x5555, 0x80) => FlashState::Erase,
+            (FlashState::Unlock2, 0x5555, 0x90) => {
+                self.flash_id = true;
+                FlashState::Ready
+            }
+            (FlashState::Unlock2, 0x5555, 0xA0) => FlashState::Program,
+            (FlashState::Program, _, _) => {
+                // Programming can only clear bits
+                self.flash[offset] &= data;
+                self.flash_dirty = true;
+                FlashState::Ready
+            }
+            (FlashState::Erase, 0x5555, 0xAA) => FlashState::EraseUnlock1,
+            (FlashState::EraseUnlock1, 0x2AAA, 0x55) => FlashState::EraseUnlock2,
+            (FlashState::EraseUnlock2, 0x5555, 0x10) => {
+                self.flash.fill(0xFF);
+                self.flash_dirty = true;
+                FlashState::Ready
+            }
+            (FlashState::EraseUnlock2, _, 0x30) => {
+                let start = offset & !(FLASH_SECTOR_SIZE - 1);
+                self.flash[start..start + FLASH_SECTOR_SIZE].fill(0xFF);
+                self.flash_dirty = true;
+                FlashState::Ready
+            }
+            _ => FlashState::Ready,
+        };
+    }
+}
+
+impl Mbc for Mbc6 {
+    fn read_rom(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x3FFF => self.rom.get(address as usize).copied().unwrap_or(0xFF),
+            _ => {
+                let window = Self::window(address);
+                let bank = self.rom_banks[window];
+                match self.flash_select[window] {
+                    true => {
+                        self.read_flash(Self::offset(bank, address, HALF_BANK_SIZE, FLASH_SIZE))
+                    }
+                    false => {
+                        let offset = Self::offset(bank, address, HALF_BANK_SIZE, self.rom.len());
+                        self.rom.get(offset).copied().unwrap_or(0xFF)
+                    }
+                }
+            }
+        }
+    }
+
+    fn write_rom(&mut self, address: u16, data: u8) {
+        match address {
+            0x0000..=0x03FF => self.ram_enable = data & 0x0F == 0x0A,
+            0x0400..=0x07FF => self.ram_banks[0] = data,
+            0x0800..=0x0BFF => self.ram_banks[1] = data,
+            0x0C00..=0x0FFF => self.flash_enable = data & 0x01 != 0,
+            0x1000 => self.flash_write_enable = data & 0x01 != 0,
+            0x2000..=0x27FF => self.rom_banks[0] = data,
+            0x2800..=0x2FFF => self.flash_select[0] = data == FLASH_SELECT,
+            0x3000..=0x37FF => self.rom_banks[1] = data,
+            0x3800..=0x3FFF => self.flash_select[1] = data == FLASH_SELECT,
+            0x4000..=0x7FFF => {
+                let window = Self::window(address);
+                if self.flash_select[window] {
+                    let bank = self.rom_banks[window];
+                    self.write_flash(
+                        Self::offset(bank, address, HALF_BANK_SIZE, FLASH_SIZE),
+                        data,
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        match self.ram_enable && !self.ram.is_empty() {
+            true => self.ram[self.ram_offset(address)],
+            false => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, data: u8) -> bool {
+        if !self.ram_enable || self.ram.is_empty() {
+            return false;
+        }
+
+        let offset = self.ram_offset(address);
+        std::mem::replace(&mut self.ram[offset], data) != data
+    }
+
+    fn take_flash_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.flash_dirty)
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    /// Flash is appended after the RAM
+    fn save_data(&self) -> Vec<u8> {
+        [self.ram.as_slice(), self.flash.as_slice()].concat()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        let (ram, flash) = data.split_at(self.ram.len().min(data.len()));
+        self.ram[..ram.len()].copy_from_slice(ram);
+        let len = flash.len().min(FLASH_SIZE);
+        self.flash[..len].copy_from_slice(&flash[..len]);
+    }
+}
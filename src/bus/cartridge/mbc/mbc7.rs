@@ -0,0 +1,283 @@
+use super::{rom_offset, Mbc};
+
+const EEPROM_SIZE: usize = 256;
+
+const ACCEL_CENTER: f32 = 0x81D0 as f32;
+const ACCEL_PER_G: f32 = 0x70 as f32;
+const ACCEL_ERASED: u16 = 0x8000;
+
+const CS: u8 = 1 << 7;
+const CLK: u8 = 1 << 6;
+const DI: u8 = 1 << 1;
+const DO: u8 = 1 << 0;
+
+pub struct Mbc7 {
+    rom: Vec<u8>,
+    ram_enable: [bool; 2],
+    rom_bank: u8,
+    tilt: (f32, f32),
+    latched: bool,
+    x_latch: u16,
+    y_latch: u16,
+    eeprom: Eeprom,
+}
+
+impl Mbc7 {
+    pub fn new(rom: Vec<u8>) -> Self {
+        Self {
+            rom,
+            ram_enable: [false; 2],
+            rom_bank: 1,
+            tilt: (0.0, 0.0),
+            latched: false,
+            x_latch: ACCEL_ERASED,
+            y_latch: ACCEL_ERASED,
+            eeprom: Eeprom::new(),
+        }
+    }
+
+    fn accel(value: f32) -> u16 {
+        (ACCEL_CENTER + ACCEL_PER_G * value.clamp(-1.0, 1.0)) as u16
+    }
+
+    fn registers_enabled(&self) -> bool {
+        self.ram_enable[0] && self.ram_enable[1]
+    }
+}
+
+impl Mbc for Mbc7 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank as usize,
+        };
+        let offset = rom_offset(&self.rom, bank, address);
+        self.rom.get(offset).copied().unwrap_or(0xFF)
+    }
+
+    fn write_rom(&mut self, address: u16, data: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enable[0] = data == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = data & 0x7F,
+            0x4000..=0x5FFF => self.ram_enable[1] = data == 0x40,
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.registers_enabled() || address >= 0xB000 {
+            return 0xFF;
+        }
+
+        match (address >> 4) & 0x0F {
+            0x2 => self.x_latch as u8,
+            0x3 => (self.x_latch >> 8) as u8,
+            0x4 => self.y_latch as u8,
+            0x5 => (self.y_latch >> 8) as u8,
+            0x6 => 0x00,
+            0x8 => self.eeprom.read(),
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, data: u8) -> bool {
+        if !self.registers_enabled() || address >= 0xB000 {
+            return false;
+        }
+
+        match (address >> 4) & 0x0F {
+            0x0 if data == 0x55 => {
+                self.latched = false;
+                self.x_latch = ACCEL_ERASED;
+                self.y_latch = ACCEL_ERASED;
+            }
+            0x1 if data == 0xAA && !self.latched => {
+                self.latched = true;
+                self.x_latch = Self::accel(self.tilt.0);
+                self.y_latch = Self::accel(self.tilt.1);
+            }
+            0x8 => {
+                let before = self.eeprom.data;
+                self.eeprom.write(data);
+                return self.eeprom.data != before;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn set_tilt(&mut self, x: f32, y: f32) {
+        self.tilt = (x, y);
+    }
+
+    fn has_accelerometer(&self) -> bool {
+        true
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.eeprom.data
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.eeprom.data
+    }
+}
+
+#[derive(Clone, Copy)]
+enum EepromState {
+    Idle,
+    Command {
+        bits: u8,
+        value: u16,
+    },
+    Write {
+        address: Option<u8>,
+        bits: u8,
+        value: u16,
+    },
+    Read {
+        bits: u8,
+        value: u16,
+    },
+}
+
+/// 93LC56 serial EEPROM in x16 organization (128 words)
+struct Eeprom {
+    data: [u8; EEPROM_SIZE],
+    state: EepromState,
+    write_enable: bool,
+    pins: u8,
+}
+
+impl Eeprom {
+    const fn new() -> Self {
+        Self {
+            data: [0xFF; EEPROM_SIZE],
+            state: EepromState::Idle,
+            write_enable: false,
+            pins: DO,
+        }
+    }
+
+    const fn read(&self) -> u8 {
+        self.pins
+    }
+
+    fn word(&self, address: u8) -> u16 {
+        let i = (address as usize & 0x7F) * 2;
+        u16::from_le_bytes([self.data[i], self.data[i + 1]])
+    }
+
+    fn set_word(&mut self, address: u8, value: u16) {
+        let i = (address as usize & 0x7F) * 2;
+        self.data[i..i + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn write(&mut self, data: u8) {
+        let rising = self.pins & CLK == 0 && data & CLK != 0;
+        self.pins = (self.pins & DO) | (data & (CS | CLK | DI));
+
+        if data & CS == 0 {
+            self.state = EepromState::Idle;
+            self.pins |= DO;
+            return;
+        }
+
+        if rising {
+            self.clock(data & DI != 0);
+        }
+    }
+
+    fn clock(&mut self, di: bool) {
+        self.state = match self.state {
+            EepromState::Idle if di => EepromState::Command { bits: 0, value: 0 },
+            EepromState::Idle => EepromState::Idle,
+            EepromState::Command { bits, value } => {
+                let value = (value << 1) | di as u16;
+                match bits + 1 {
+                    10 => self.command(value),
+                    bits => EepromState::Command { bits, value },
+                }
+            }
+            EepromState::Write {
+                address,
+                bits,
+                value,
+            } => {
+                let value = (value << 1) | di as u16;
+                match bits + 1 {
+                    16 => {
+                        if self.write_enable {
+                            match address {
+                                Some(address) => self.set_word(address, value),
+                                None => (0..0x80).for_each(|a| self.set_word(a, value)),
+                            }
+                        }
+                        self.pins |= DO;
+                        EepromState::Idle
+                    }
+                    bits => EepromState::Write {
+                        address,
+                        bits,
+                        value,
+                    },
+                }
+            }
+            EepromState::Read { bits, value } => {
+                self.pins = (self.pins & !DO) | (value >> 15) as u8;
+                match bits - 1 {
+                    0 => EepromState::Idle,
+                    bits => EepromState::Read {
+                        bits,
+                        value: value << 1,
+                    },
+                }
+            }
+        };
+    }
+
+    fn command(&mut self, value: u16) -> EepromState {
+        let address = value as u8;
+        match (value >> 8) & 0b11 {
+            0b10 => {
+                self.pins &= !DO;
+                EepromState::Read {
+                    bits: 16,
+                    value: self.word(address),
+                }
+            }
+            0b01 => EepromState::Write {
+                address: Some(address),
+                bits: 0,
+                value: 0,
+            },
+            0b11 => {
+                if self.write_enable {
+                    self.set_word(address, 0xFFFF);
+                }
+                EepromState::Idle
+            }
+            _ => match address >> 6 {
+                0b11 => {
+                    self.write_enable = true;
+                    EepromState::Idle
+                }
+                0b00 => {
+                    self.write_enable = false;
+                    EepromState::Idle
+                }
+                0b10 => {
+                    if self.write_enable {
+                        self.data = [0xFF; EEPROM_SIZE];
+                    }
+                    EepromState::Idle
+                }
+                _ => EepromState::Write {
+                    address: None,
+                    bits: 0,
+                    value: 0,
+                },
+            },
+        }
+    }
+}
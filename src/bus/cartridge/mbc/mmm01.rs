@@ -0,0 +1,134 @@
+use super::{ram_offset, rom_offset, Mbc};
+
+const MAP_ENABLE: u8 = 1 << 6;
+const MODE_WRITE_DISABLE: u8 = 1 << 6;
+
+/// Multicart mapper that boots into a menu mapped to the last 32 KiB of ROM.
+/// Once the menu sets the map enable bit, the outer bank bits and masks are
+/// locked and the cartridge behaves like an MBC1 confined to the chosen game.
+pub struct Mmm01 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    mapped: bool,
+    ram_enable: bool,
+    rom_bank: u16,
+    rom_mask: u16,
+    ram_bank: u8,
+    ram_mask: u8,
+    mode: bool,
+    mode_lock: bool,
+}
+
+impl Mmm01 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        Self {
+            rom,
+            ram: vec![0; ram_size],
+            mapped: false,
+            ram_enable: false,
+            rom_bank: 0,
+            rom_mask: 0,
+            ram_bank: 0,
+            ram_mask: 0,
+            mode: false,
+            mode_lock: false,
+        }
+    }
+
+    fn rom_bank(&self, address: u16) -> usize {
+        if !self.mapped {
+            return match address {
+                0x0000..=0x3FFF => 0x1FE,
+                _ => 0x1FF,
+            };
+        }
+
+        // Bits 1-4 of the mask freeze the matching bank lines
+        let frozen = self.rom_mask & 0x1E;
+        match address {
+            0x0000..=0x3FFF => ((self.rom_bank & !0x1F) | (self.rom_bank & frozen)) as usize,
+            _ => match self.rom_bank & 0x1F & !frozen {
+                0 => (self.rom_bank | 1) as usize,
+                _ => self.rom_bank as usize,
+            },
+        }
+    }
+
+    fn ram_bank(&self) -> usize {
+        match self.mode || !self.mapped {
+            true => self.ram_bank as usize,
+            false => (self.ram_bank & !0x03) as usize,
+        }
+    }
+
+    fn set_masked(value: &mut u8, data: u8, mask: u8) {
+        *value = (*value & mask) | (data & !mask);
+    }
+}
+
+impl Mbc for Mmm01 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let offset = rom_offset(&self.rom, self.rom_bank(address), address);
+        self.rom.get(offset).copied().unwrap_or(0xFF)
+    }
+
+    fn write_rom(&mut self, address: u16, data: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                self.ram_enable = data & 0x0F == 0x0A;
+                if !self.mapped {
+                    self.ram_mask = (data >> 4) & 0x03;
+                    self.mapped = data & MAP_ENABLE != 0;
+                }
+            }
+            0x2000..=0x3FFF => {
+                let low = (data as u16 & 0x1F & !self.rom_mask) | (self.rom_bank & self.rom_mask);
+                self.rom_bank = (self.rom_bank & !0x1F) | low;
+                if !self.mapped {
+                    self.rom_bank = (self.rom_bank & !0x60) | (data as u16 & 0x60);
+                }
+            }
+            0x4000..=0x5FFF => {
+                Self::set_masked(&mut self.ram_bank, data & 0x03, self.ram_mask | !0x03);
+                if !self.mapped {
+                    self.ram_bank = (self.ram_bank & 0x03) | (data & 0x0C);
+                    self.rom_bank = (self.rom_bank & 0x7F) | ((data as u16 & 0x30) << 3);
+                    self.mode_lock = data & MODE_WRITE_DISABLE != 0;
+                }
+            }
+            0x6000..=0x7FFF => {
+                if !self.mode_lock {
+                    self.mode = data & 0x01 != 0;
+                }
+                if !self.mapped {
+                    self.rom_mask = (data as u16 & 0x3C) >> 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        match self.ram_enable && !self.ram.is_empty() {
+            true => self.ram[ram_offset(&self.ram, self.ram_bank(), address)],
+            false => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, data: u8) -> bool {
+        if !self.ram_enable || self.ram.is_empty() {
+            return false;
+        }
+
+        let offset = ram_offset(&self.ram, self.ram_bank(), address);
+        std::mem::replace(&mut self.ram[offset], data) != data
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+}
@@ -0,0 +1,57 @@
+pub use self::{
+    huc1::HuC1, huc3::HuC3, mbc3::Mbc3, mbc5::Mbc5, mbc6::Mbc6, mbc7::Mbc7, mmm01::Mmm01,
+    rom_only::RomOnly,
+};
+
+mod huc1;
+mod huc3;
+mod mbc3;
+mod mbc5;
+mod mbc6;
+mod mbc7;
+mod mmm01;
+mod rom_only;
+mod rtc;
+
+pub const ROM_BANK_SIZE: usize = 0x4000;
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+pub trait Mbc {
+    fn read_rom(&self, address: u16) -> u8;
+    fn write_rom(&mut self, address: u16, data: u8);
+    fn read_ram(&self, address: u16) -> u8;
+    /// Returns whether battery-backed data changed
+    fn write_ram(&mut self, address: u16, data: u8) -> bool;
+    fn rumble(&self) -> bool {
+        false
+    }
+    fn set_tilt(&mut self, _x: f32, _y: f32) {}
+    fn has_accelerometer(&self) -> bool {
+        false
+    }
+    /// Returns whether flash saved with the RAM changed since the last call
+    fn take_flash_dirty(&mut self) -> bool {
+        false
+    }
+    fn ram(&self) -> &[u8];
+    fn ram_mut(&mut self) -> &mut [u8];
+    fn save_data(&self) -> Vec<u8> {
+        self.ram().to_vec()
+    }
+    fn load_save_data(&mut self, data: &[u8]) {
+        let ram = self.ram_mut();
+        let len = ram.len().min(data.len());
+        ram[..len].copy_from_slice(&data[..len]);
+    }
+}
+
+pub fn rom_offset(rom: &[u8], bank: usize, address: u16) -> usize {
+    let bank = bank % (rom.len() / ROM_BANK_SIZE).max(1);
+    bank * ROM_BANK_SIZE + (address as usize & (ROM_BANK_SIZE - 1))
+}
+
+/// Callers must make sure `ram` is not empty
+pub fn ram_offset(ram: &[u8], bank: usize, address: u16) -> usize {
+    let bank = bank % (ram.len() / RAM_BANK_SIZE).max(1);
+    (bank * RAM_BANK_SIZE + (address as usize & (RAM_BANK_SIZE - 1))) % ram.len()
+}
@@ -0,0 +1,47 @@
+use super::Mbc;
+
+pub struct RomOnly {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+}
+
+impl RomOnly {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        Self {
+            rom,
+            ram: vec![0; ram_size],
+        }
+    }
+}
+
+impl Mbc for RomOnly {
+    fn read_rom(&self, address: u16) -> u8 {
+        self.rom.get(address as usize).copied().unwrap_or(0xFF)
+    }
+
+    fn write_rom(&mut self, _address: u16, _data: u8) {}
+
+    fn read_ram(&self, address: u16) -> u8 {
+        match self.ram.is_empty() {
+            true => 0xFF,
+            false => self.ram[(address as usize & 0x1FFF) % self.ram.len()],
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, data: u8) -> bool {
+        if self.ram.is_empty() {
+            return false;
+        }
+
+        let offset = (address as usize & 0x1FFF) % self.ram.len();
+        std::mem::replace(&mut self.ram[offset], data) != data
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+}
@@ -0,0 +1,163 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// BGB/VBA-M footer: current and latched registers as 5 little-endian
+/// u32 each, followed by a 64-bit UNIX timestamp
+pub const FOOTER_SIZE: usize = 48;
+/// Older variant of the footer with a 32-bit timestamp
+const FOOTER_SIZE_SHORT: usize = 44;
+
+const SECONDS: u8 = 0x08;
+const MINUTES: u8 = 0x09;
+const HOURS: u8 = 0x0A;
+const DAYS_LO: u8 = 0x0B;
+const DAYS_HI: u8 = 0x0C;
+
+const HALT: u8 = 1 << 6;
+const DAY_CARRY: u8 = 1 << 7;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn write_footer(current: [u8; 5], latched: [u8; 5], timestamp: u64) -> [u8; FOOTER_SIZE] {
+    let mut footer = [0; FOOTER_SIZE];
+    for (i, &reg) in current.iter().chain(latched.iter()).enumerate() {
+        footer[i * 4..i * 4 + 4].copy_from_slice(&(reg as u32).to_le_bytes());
+    }
+    footer[40..].copy_from_slice(&timestamp.to_le_bytes());
+    footer
+}
+
+/// Returns the current registers, latched registers and timestamp if `data`
+/// is a 44 or 48 byte footer
+pub fn read_footer(data: &[u8]) -> Option<([u8; 5], [u8; 5], u64)> {
+    let timestamp = match data.len() {
+        FOOTER_SIZE => u64::from_le_bytes(data[40..48].try_into().ok()?),
+        FOOTER_SIZE_SHORT => u32::from_le_bytes(data[40..44].try_into().ok()?) as u64,
+        _ => return None,
+    };
+
+    let reg = |i: usize| data[i * 4];
+    let current = [reg(0), reg(1), reg(2), reg(3), reg(4)];
+    let latched = [reg(5), reg(6), reg(7), reg(8), reg(9)];
+
+    Some((current, latched, timestamp))
+}
+
+/// MBC3 real time clock, advanced from the host clock
+pub struct Rtc {
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    days: u16,
+    halt: bool,
+    carry: bool,
+    latched: [u8; 5],
+    timestamp: u64,
+}
+
+impl Rtc {
+    pub fn new() -> Self {
+        Self {
+            seconds: 0,
+            minutes: 0,
+            hours: 0,
+            days: 0,
+            halt: false,
+            carry: false,
+            latched: [0; 5],
+            timestamp: now(),
+        }
+    }
+
+    fn update(&mut self) {
+        let now = now();
+        let elapsed = now.saturating_sub(self.timestamp);
+        self.timestamp = now;
+        if self.halt || elapsed == 0 {
+            return;
+        }
+
+        let total = self.seconds as u64
+            + self.minutes as u64 * 60
+            + self.hours as u64 * 3600
+            + self.days as u64 * SECONDS_PER_DAY
+            + elapsed;
+
+        self.seconds = (total % 60) as u8;
+        self.minutes = (total / 60 % 60) as u8;
+        self.hours = (total / 3600 % 24) as u8;
+        let days = total / SECONDS_PER_DAY;
+        if days > 0x1FF {
+            self.carry = true;
+        }
+        self.days = (days & 0x1FF) as u16;
+    }
+
+    fn registers(&self) -> [u8; 5] {
+        let mut days_hi = (self.days >> 8) as u8 & 0x01;
+        if self.halt {
+            days_hi |= HALT;
+        }
+        if self.carry {
+            days_hi |= DAY_CARRY;
+        }
+        [
+            self.seconds,
+            self.minutes,
+            self.hours,
+            self.days as u8,
+            days_hi,
+        ]
+    }
+
+    fn set_registers(&mut self, regs: [u8; 5]) {
+        self.seconds = regs[0] & 0x3F;
+        self.minutes = regs[1] & 0x3F;
+        self.hours = regs[2] & 0x1F;
+        self.days = regs[3] as u16 | ((regs[4] as u16 & 0x01) << 8);
+        self.halt = regs[4] & HALT != 0;
+        self.carry = regs[4] & DAY_CARRY != 0;
+    }
+
+    pub fn latch(&mut self) {
+        self.update();
+        self.latched = self.registers();
+    }
+
+    pub fn read(&self, reg: u8) -> u8 {
+        match reg {
+            SECONDS..=DAYS_HI => self.latched[(reg - SECONDS) as usize],
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write(&mut self, reg: u8, data: u8) {
+        self.update();
+        let mut regs = self.registers();
+        match reg {
+            SECONDS | MINUTES | HOURS | DAYS_LO | DAYS_HI => regs[(reg - SECONDS) as usize] = data,
+            _ => return,
+        }
+        self.set_registers(regs);
+    }
+
+    pub fn footer(&self) -> [u8; FOOTER_SIZE] {
+        write_footer(self.registers(), self.latched, self.timestamp)
+    }
+
+    /// Restores the clock and catches up by the real time elapsed since the save
+    pub fn load_footer(&mut self, data: &[u8]) {
+        if let Some((current, latched, timestamp)) = read_footer(data) {
+            self.set_registers(current);
+            self.latched = latched;
+            self.timestamp = timestamp;
+            self.update();
+        }
+    }
+}
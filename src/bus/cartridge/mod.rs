@@ -1,21 +1,71 @@
-use self::header::{Header, HEADER_LOC, HEADER_SIZE};
+use super::cheats::GameGenie;
 
-mod header;
+use self::{
+    header::{CartridgeType, Header, HeaderError},
+    mbc::{ram_offset, HuC1, HuC3, Mbc, Mbc3, Mbc5, Mbc6, Mbc7, Mmm01, RomOnly},
+};
+
+pub mod header;
+mod mbc;
+
+const ROM_START: u16 = 0x0000;
+const ROM_END: u16 = 0x7FFF;
+
+const RAM_START: u16 = 0xA000;
+const RAM_END: u16 = 0xBFFF;
+
+/// MMM01 multicarts boot into a menu stored in the last 32 KiB of ROM
+const MMM01_MENU_SIZE: usize = 0x8000;
 
 pub struct Cartridge {
     header: Header,
-    rom: Vec<u8>,
+    mbc: Box<dyn Mbc>,
+    dirty: bool,
+    game_genie: Vec<GameGenie>,
 }
 
 impl Cartridge {
-    pub fn new(rom: Vec<u8>) -> Self {
-        let mut header = [0; HEADER_SIZE];
-        header.copy_from_slice(&rom[HEADER_LOC..HEADER_LOC + HEADER_SIZE]);
+    pub fn new(rom: Vec<u8>) -> Result<Self, HeaderError> {
+        let header = Header::parse_at(&rom, header_base(&rom))?;
 
-        Self {
-            header: Header::new(header),
-            rom,
-        }
+        let ram_size = header.ram_size.bytes();
+        let cart_type = header.cart_type;
+        let mbc: Box<dyn Mbc> = match cart_type {
+            CartridgeType::Mmm01 | CartridgeType::Mmm01Ram | CartridgeType::Mmm01RamBattery => {
+                Box::new(Mmm01::new(rom, ram_size))
+            }
+            CartridgeType::Mbc3TimerBattery
+            | CartridgeType::Mbc3TimerRamBattery
+            | CartridgeType::Mbc3
+            | CartridgeType::Mbc3Ram
+            | CartridgeType::Mbc3RamBattery => {
+                Box::new(Mbc3::new(rom, ram_size, cart_type.has_rtc()))
+            }
+            CartridgeType::Mbc5
+            | CartridgeType::Mbc5Ram
+            | CartridgeType::Mbc5RamBattery
+            | CartridgeType::Mbc5Rumble
+            | CartridgeType::Mbc5RumbleRam
+            | CartridgeType::Mbc5RumbleRamBattery => {
+                Box::new(Mbc5::new(rom, ram_size, cart_type.has_rumble()))
+            }
+            CartridgeType::Mbc6 => Box::new(Mbc6::new(rom, ram_size)),
+            CartridgeType::Mbc7SensorRumbleRamBattery => Box::new(Mbc7::new(rom)),
+            CartridgeType::HuC3 => Box::new(HuC3::new(rom, ram_size)),
+            CartridgeType::HuC1RamBattery => Box::new(HuC1::new(rom, ram_size)),
+            _ => Box::new(RomOnly::new(rom, ram_size)),
+        };
+
+        Ok(Self {
+            header,
+            mbc,
+            dirty: false,
+            game_genie: vec![],
+        })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
     }
 
     pub fn print_header(&self) {
@@ -26,10 +76,96 @@ impl Cartridge {
     }
 
     pub fn read(&self, address: u16) -> u8 {
-        self.rom[address as usize]
+        match address {
+            ROM_START..=ROM_END => {
+                let data = self.mbc.read_rom(address);
+                self.game_genie
+                    .iter()
+                    .fold(data, |data, gg| gg.apply(address, data))
+            }
+            RAM_START..=RAM_END => self.mbc.read_ram(address),
+            _ => 0xFF,
+        }
     }
 
     pub fn write(&mut self, address: u16, data: u8) {
-        self.rom[address as usize] = data;
+        match address {
+            ROM_START..=ROM_END => {
+                self.mbc.write_rom(address, data);
+                self.dirty |= self.mbc.take_flash_dirty();
+            }
+            RAM_START..=RAM_END => self.dirty |= self.mbc.write_ram(address, data),
+            _ => {}
+        }
+    }
+
+    /// Writes external RAM `bank` directly, whichever bank is mapped and even
+    /// when RAM is disabled
+    pub fn write_ram_bank(&mut self, bank: u8, address: u16, data: u8) {
+        let ram = self.mbc.ram_mut();
+        if ram.is_empty() {
+            return;
+        }
+        let offset = ram_offset(ram, bank as usize, address);
+        self.dirty |= std::mem::replace(&mut ram[offset], data) != data;
+    }
+
+    pub fn set_game_genie(&mut self, codes: Vec<GameGenie>) {
+        self.game_genie = codes;
+    }
+
+    pub fn rumble(&self) -> bool {
+        self.mbc.rumble()
+    }
+
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        self.mbc.set_tilt(x, y);
+    }
+
+    pub fn has_accelerometer(&self) -> bool {
+        self.mbc.has_accelerometer()
+    }
+
+    pub fn has_save(&self) -> bool {
+        self.header.cart_type.has_save()
+    }
+
+    /// Returns whether battery-backed data changed since the last call
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// All external RAM banks back to back
+    pub fn ram(&self) -> &[u8] {
+        self.mbc.ram()
+    }
+
+    /// Raw battery-backed memory, as stored in .sav files
+    pub fn save_data(&self) -> Vec<u8> {
+        self.mbc.save_data()
+    }
+
+    pub fn load_save_data(&mut self, data: &[u8]) {
+        self.mbc.load_save_data(data);
+    }
+}
+
+/// Offset of the header describing `rom`. The header of an MMM01 dump is
+/// the menu's one in the last 32 KiB, bank 0 holds the header of the first
+/// game.
+pub fn header_base(rom: &[u8]) -> usize {
+    let base = match rom.len().checked_sub(MMM01_MENU_SIZE) {
+        Some(base) if base > 0 => base,
+        _ => return 0,
+    };
+    let mmm01 = Header::parse_at(rom, base).is_ok_and(|header| {
+        matches!(
+            header.cart_type,
+            CartridgeType::Mmm01 | CartridgeType::Mmm01Ram | CartridgeType::Mmm01RamBattery
+        )
+    });
+    match mmm01 {
+        true => base,
+        false => 0,
     }
 }
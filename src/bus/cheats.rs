@@ -0,0 +1,209 @@
+use std::{error::Error, fmt};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheatError(pub String);
+
+impl fmt::Display for CheatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid cheat code \"{}\"", self.0)
+    }
+}
+
+impl Error for CheatError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameGenie {
+    pub address: u16,
+    pub data: u8,
+    pub compare: Option<u8>,
+}
+
+impl GameGenie {
+    /// Decodes `ABC-DEF` or `ABC-DEF-GHI`, dashes are optional
+    fn parse(code: &str) -> Option<Self> {
+        let digits = hex_digits(code)?;
+        if digits.len() != 6 && digits.len() != 9 {
+            return None;
+        }
+
+        let data = (digits[0] << 4) | digits[1];
+        let address = (((digits[5] ^ 0xF) as u16) << 12)
+            | ((digits[2] as u16) << 8)
+            | ((digits[3] as u16) << 4)
+            | digits[4] as u16;
+        let compare = match digits.len() {
+            9 => Some(((digits[6] << 4) | digits[8]).rotate_right(2) ^ 0xBA),
+            _ => None,
+        };
+
+        // Game Genie codes can only patch ROM
+        match address < 0x8000 {
+            true => Some(Self {
+                address,
+                data,
+                compare,
+            }),
+            false => None,
+        }
+    }
+
+    pub fn apply(&self, address: u16, data: u8) -> u8 {
+        match address == self.address && self.compare.is_none_or(|c| c == data) {
+            true => self.data,
+            false => data,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameShark {
+    /// RAM bank written, `None` for whichever bank is mapped
+    pub bank: Option<u8>,
+    pub address: u16,
+    pub data: u8,
+}
+
+impl GameShark {
+    /// Decodes `TTVVLLHH`, where TT is the code type and VV the value written
+    /// to the little-endian address HHLL
+    fn parse(code: &str) -> Option<Self> {
+        let digits = hex_digits(code)?;
+        if digits.len() != 8 {
+            return None;
+        }
+
+        let byte = |i: usize| (digits[i] << 4) | digits[i + 1];
+        // Type $01 writes through the current mapping, $8x/$9x write bank x of
+        // the external or work RAM the address belongs to
+        let bank = match byte(0) {
+            0x01 => None,
+            code @ 0x80..=0x97 => Some(code & 0x0F),
+            _ => return None,
+        };
+        let address = u16::from_le_bytes([byte(4), byte(6)]);
+
+        // GameShark codes can only patch RAM
+        match address {
+            0xA000..=0xDFFF | 0xFF80..=0xFFFE => Some(Self {
+                bank,
+                address,
+                data: byte(2),
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheatKind {
+    GameGenie(GameGenie),
+    GameShark(GameShark),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cheat {
+    pub code: String,
+    pub description: String,
+    pub kind: CheatKind,
+    pub enabled: bool,
+}
+
+impl Cheat {
+    pub fn parse(code: &str) -> Result<Self, CheatError> {
+        let kind = GameShark::parse(code)
+            .map(CheatKind::GameShark)
+            .or_else(|| GameGenie::parse(code).map(CheatKind::GameGenie))
+            .ok_or_else(|| CheatError(code.to_string()))?;
+
+        Ok(Self {
+            code: code.to_string(),
+            description: String::new(),
+            kind,
+            enabled: true,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct Cheats {
+    list: Vec<Cheat>,
+}
+
+impl Cheats {
+    pub const fn new() -> Self {
+        Self { list: vec![] }
+    }
+
+    /// Parses a cheat file: one `CODE [description]` per line, lines starting
+    /// with `#` are comments and a leading `-` adds the cheat disabled.
+    /// Invalid lines are skipped and returned with their line number.
+    pub fn load(&mut self, text: &str) -> Vec<(usize, CheatError)> {
+        let mut errors = vec![];
+        for (number, line) in text.lines().map(str::trim).enumerate() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (enabled, line) = match line.strip_prefix('-') {
+                Some(line) => (false, line.trim_start()),
+                None => (true, line),
+            };
+            let (code, description) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+
+            match Cheat::parse(code) {
+                Ok(mut cheat) => {
+                    cheat.description = description.trim().to_string();
+                    cheat.enabled = enabled;
+                    self.list.push(cheat);
+                }
+                Err(e) => errors.push((number + 1, e)),
+            }
+        }
+
+        errors
+    }
+
+    pub fn add(&mut self, cheat: Cheat) {
+        self.list.push(cheat);
+    }
+
+    pub fn list(&self) -> &[Cheat] {
+        &self.list
+    }
+
+    /// Returns the new state of the cheat, or `None` if `index` is out of range
+    pub fn toggle(&mut self, index: usize) -> Option<bool> {
+        let cheat = self.list.get_mut(index)?;
+        cheat.enabled = !cheat.enabled;
+        Some(cheat.enabled)
+    }
+
+    pub fn game_genie(&self) -> Vec<GameGenie> {
+        self.enabled()
+            .filter_map(|kind| match kind {
+                CheatKind::GameGenie(gg) => Some(gg),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn game_shark(&self) -> Vec<GameShark> {
+        self.enabled()
+            .filter_map(|kind| match kind {
+                CheatKind::GameShark(gs) => Some(gs),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn enabled(&self) -> impl Iterator<Item = CheatKind> + '_ {
+        self.list.iter().filter(|c| c.enabled).map(|c| c.kind)
+    }
+}
+
+fn hex_digits(code: &str) -> Option<Vec<u8>> {
+    code.chars()
+        .filter(|&c| c != '-')
+        .map(|c| c.to_digit(16).map(|d| d as u8))
+        .collect()
+}
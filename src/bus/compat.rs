@@ -0,0 +1,213 @@
+use super::joypad::GbButton;
+
+/// Palettes the CGB boot ROM gives DMG games, in BGR555 from lightest to
+/// darkest shade
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompatPalette {
+    pub name: &'static str,
+    pub bg: [u16; 4],
+    pub obj0: [u16; 4],
+    pub obj1: [u16; 4],
+}
+
+/// Palette data of the CGB boot ROM, 4 colors each
+const PALETTES: [u16; 30 * 4] = [
+    0x7FFF, 0x32BF, 0x00D0, 0x0000, // 0
+    0x639F, 0x4279, 0x15B0, 0x04CB, // 1
+    0x7FFF, 0x6E31, 0x454A, 0x0000, // 2
+    0x7FFF, 0x1BEF, 0x0200, 0x0000, // 3
+    0x7FFF, 0x421F, 0x1CF2, 0x0000, // 4
+    0x7FFF, 0x5294, 0x294A, 0x0000, // 5
+    0x7FFF, 0x03FF, 0x012F, 0x0000, // 6
+    0x7FFF, 0x03EF, 0x01D6, 0x0000, // 7
+    0x7FFF, 0x42B5, 0x3DC8, 0x0000, // 8
+    0x7E74, 0x03FF, 0x0180, 0x0000, // 9
+    0x67FF, 0x77AC, 0x1A13, 0x2D6B, // 10
+    0x7ED6, 0x4BFF, 0x2175, 0x0000, // 11
+    0x53FF, 0x4A5F, 0x7E52, 0x0000, // 12
+    0x4FFF, 0x7ED2, 0x3A4C, 0x1CE0, // 13
+    0x03ED, 0x7FFF, 0x255F, 0x0000, // 14
+    0x036A, 0x021F, 0x03FF, 0x7FFF, // 15
+    0x7FFF, 0x01DF, 0x0112, 0x0000, // 16
+    0x231F, 0x035F, 0x00F2, 0x0009, // 17
+    0x7FFF, 0x03EA, 0x011F, 0x0000, // 18
+    0x299F, 0x001A, 0x000C, 0x0000, // 19
+    0x7FFF, 0x027F, 0x001F, 0x0000, // 20
+    0x7FFF, 0x03E0, 0x0206, 0x0120, // 21
+    0x7FFF, 0x7EEB, 0x001F, 0x7C00, // 22
+    0x7FFF, 0x3FFF, 0x7E00, 0x001F, // 23
+    0x7FFF, 0x03FF, 0x001F, 0x0000, // 24
+    0x03FF, 0x001F, 0x000C, 0x0000, // 25
+    0x7FFF, 0x033F, 0x0193, 0x0000, // 26
+    0x0000, 0x4200, 0x037F, 0x7FFF, // 27
+    0x7FFF, 0x7E8C, 0x7C00, 0x0000, // 28
+    0x7FFF, 0x1BEF, 0x6180, 0x0000, // 29
+];
+
+const fn comb(obj0: usize, obj1: usize, bg: usize) -> (usize, usize, usize) {
+    (obj0 * 4, obj1 * 4, bg * 4)
+}
+
+/// A few combinations start in the middle of a palette
+const fn raw(obj0: usize, obj1: usize, bg: usize) -> (usize, usize, usize) {
+    (obj0, obj1, bg)
+}
+
+/// OBJ0, OBJ1 and BG colors of each combination, as offsets into `PALETTES`
+const COMBINATIONS: [(usize, usize, usize); 51] = [
+    comb(4, 4, 29),                    // 0
+    comb(18, 18, 18),                  // 1
+    comb(20, 20, 20),                  // 2
+    comb(24, 24, 24),                  // 3
+    comb(9, 9, 9),                     // 4
+    comb(0, 0, 0),                     // 5
+    comb(27, 27, 27),                  // 6
+    comb(5, 5, 5),                     // 7
+    comb(12, 12, 12),                  // 8
+    comb(26, 26, 26),                  // 9
+    comb(16, 8, 8),                    // 10
+    comb(4, 28, 28),                   // 11
+    comb(4, 2, 2),                     // 12
+    comb(3, 4, 4),                     // 13
+    comb(4, 29, 29),                   // 14
+    comb(28, 4, 28),                   // 15
+    comb(2, 17, 2),                    // 16
+    comb(16, 16, 8),                   // 17
+    comb(4, 4, 7),                     // 18
+    comb(4, 4, 18),                    // 19
+    comb(4, 4, 20),                    // 20
+    comb(19, 19, 9),                   // 21
+    raw(4 * 4 - 1, 4 * 4 - 1, 11 * 4), // 22
+    comb(17, 17, 2),                   // 23
+    comb(4, 4, 2),                     // 24
+    comb(4, 4, 3),                     // 25
+    comb(28, 28, 0),                   // 26
+    comb(3, 3, 0),                     // 27
+    comb(0, 0, 1),                     // 28
+    comb(18, 22, 18),                  // 29
+    comb(20, 22, 20),                  // 30
+    comb(24, 22, 24),                  // 31
+    comb(16, 22, 8),                   // 32
+    comb(17, 4, 13),                   // 33
+    raw(28 * 4 - 1, 0, 14 * 4),        // 34
+    raw(28 * 4 - 1, 4 * 4, 15 * 4),    // 35
+    comb(19, 22, 9),                   // 36
+    comb(16, 28, 10),                  // 37
+    comb(4, 23, 28),                   // 38
+    comb(17, 22, 2),                   // 39
+    comb(4, 0, 2),                     // 40
+    comb(4, 28, 3),                    // 41
+    comb(28, 3, 0),                    // 42
+    comb(3, 28, 4),                    // 43
+    comb(21, 28, 4),                   // 44
+    comb(3, 28, 0),                    // 45
+    comb(25, 3, 28),                   // 46
+    comb(0, 28, 8),                    // 47
+    comb(4, 3, 28),                    // 48
+    comb(28, 3, 6),                    // 49
+    comb(4, 28, 29),                   // 50
+];
+
+/// Title hashes known to the boot ROM, see `Header::title_hash`
+const TITLE_HASHES: [u8; 94] = [
+    0x00, 0x88, 0x16, 0x36, 0xD1, 0xDB, 0xF2, 0x3C, 0x8C, 0x92, 0x3D, 0x5C, 0x58, 0xC9, 0x3E, 0x70,
+    0x1D, 0x59, 0x69, 0x19, 0x35, 0xA8, 0x14, 0xAA, 0x75, 0x95, 0x99, 0x34, 0x6F, 0x15, 0xFF, 0x97,
+    0x4B, 0x90, 0x17, 0x10, 0x39, 0xF7, 0xF6, 0xA2, 0x49, 0x4E, 0x43, 0x68, 0xE0, 0x8B, 0xF0, 0xCE,
+    0x0C, 0x29, 0xE8, 0xB7, 0x86, 0x9A, 0x52, 0x01, 0x9D, 0x71, 0x9C, 0xBD, 0x5D, 0x6D, 0x67, 0x3F,
+    0x6B, 0xB3, 0x46, 0x28, 0xA5, 0xC6, 0xD3, 0x27, 0x61, 0x18, 0x66, 0x6A, 0xBF, 0x0D, 0xF4, 0xB3,
+    0x46, 0x28, 0xA5, 0xC6, 0xD3, 0x27, 0x61, 0x18, 0x66, 0x6A, 0xBF, 0x0D, 0xF4, 0xB3,
+];
+
+/// Hashes from this index on are shared by several games and only match
+/// together with the 4th title letter below
+const FIRST_DUPLICATE: usize = 65;
+const DUPLICATE_LETTERS: &[u8; 29] = b"BEFAARBEKEK R-URAR INAILICE R";
+
+/// Combination picked for each entry of `TITLE_HASHES`
+const TITLE_COMBINATIONS: [u8; 94] = [
+    0, 4, 5, 35, 34, 3, 31, 15, 10, 5, 19, 36, 7, 37, 30, 44, 21, 32, 31, 20, 5, 33, 13, 14, 5, 29,
+    5, 18, 9, 3, 2, 26, 25, 25, 41, 42, 26, 45, 42, 45, 36, 38, 26, 42, 30, 41, 34, 34, 5, 42, 6,
+    5, 33, 25, 42, 42, 40, 2, 16, 25, 42, 42, 5, 0, 39, 36, 22, 25, 6, 32, 12, 36, 11, 39, 18, 39,
+    24, 31, 50, 17, 46, 6, 27, 0, 47, 41, 41, 0, 0, 34, 23, 18, 29, 28,
+];
+
+/// Used for games without an entry in the title table, Right + A
+pub const DEFAULT_PALETTE: CompatPalette = CompatPalette::combination("right+a", 0);
+
+/// Palettes picked by holding a direction, optionally with A or B, during the
+/// boot logo
+pub const MANUAL_PALETTES: [CompatPalette; 12] = [
+    CompatPalette::combination("up", 5),
+    CompatPalette::combination("up+a", 43),
+    CompatPalette::combination("up+b", 28),
+    CompatPalette::combination("left", 48),
+    CompatPalette::combination("left+a", 40),
+    CompatPalette::combination("left+b", 7),
+    CompatPalette::combination("down", 8),
+    CompatPalette::combination("down+a", 3),
+    CompatPalette::combination("down+b", 49),
+    CompatPalette::combination("right", 1),
+    DEFAULT_PALETTE,
+    CompatPalette::combination("right+b", 6),
+];
+
+const fn colors(offset: usize) -> [u16; 4] {
+    [
+        PALETTES[offset],
+        PALETTES[offset + 1],
+        PALETTES[offset + 2],
+        PALETTES[offset + 3],
+    ]
+}
+
+impl CompatPalette {
+    const fn combination(name: &'static str, index: usize) -> Self {
+        let (obj0, obj1, bg) = COMBINATIONS[index];
+        Self {
+            name,
+            bg: colors(bg),
+            obj0: colors(obj0),
+            obj1: colors(obj1),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        MANUAL_PALETTES
+            .into_iter()
+            .find(|palette| palette.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Palette for a held direction and optional A or B button
+    pub fn from_buttons(direction: GbButton, modifier: Option<GbButton>) -> Option<Self> {
+        let direction = match direction {
+            GbButton::Up => "up",
+            GbButton::Left => "left",
+            GbButton::Down => "down",
+            GbButton::Right => "right",
+            _ => return None,
+        };
+        let name = match modifier {
+            Some(GbButton::A) => format!("{}+a", direction),
+            Some(GbButton::B) => format!("{}+b", direction),
+            None => direction.to_string(),
+            Some(_) => return None,
+        };
+        Self::from_name(&name)
+    }
+
+    /// Selection made by the CGB boot ROM from the title hash, see
+    /// `Header::title_hash`. Unknown titles get the default palette.
+    pub fn from_title(title_hash: u8, title: &str) -> Self {
+        let fourth = title.as_bytes().get(3).copied();
+        let index = TITLE_HASHES
+            .iter()
+            .enumerate()
+            .position(|(i, &hash)| {
+                hash == title_hash
+                    && (i < FIRST_DUPLICATE
+                        || Some(DUPLICATE_LETTERS[i - FIRST_DUPLICATE]) == fourth)
+            })
+            .unwrap_or(0);
+        Self::combination("title", TITLE_COMBINATIONS[index] as usize)
+    }
+}
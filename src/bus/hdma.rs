@@ -0,0 +1,90 @@
+const HDMA1: u16 = 0xFF51;
+const HDMA2: u16 = 0xFF52;
+const HDMA3: u16 = 0xFF53;
+const HDMA4: u16 = 0xFF54;
+const HDMA5: u16 = 0xFF55;
+
+pub const BLOCK_SIZE: u16 = 0x10;
+
+/// CGB VRAM DMA, copying 16-byte blocks either all at once (general purpose)
+/// or one per HBlank
+pub struct Hdma {
+    source: u16,
+    dest: u16,
+    /// Blocks left to copy
+    remaining: u8,
+    hblank: bool,
+}
+
+impl Hdma {
+    pub const fn new() -> Self {
+        Self {
+            source: 0,
+            dest: 0,
+            remaining: 0,
+            hblank: false,
+        }
+    }
+
+    /// HDMA5 reads the blocks left minus one, bit 7 is clear while an HBlank
+    /// transfer is active and reads $FF once a transfer completes
+    pub const fn read(&self, address: u16) -> u8 {
+        match address {
+            HDMA5 => {
+                let length = self.remaining.wrapping_sub(1) & 0x7F;
+                match self.hblank {
+                    true => length,
+                    false => 0x80 | length,
+                }
+            }
+            _ => 0xFF,
+        }
+    }
+
+    /// Returns the number of blocks of a general-purpose transfer to copy now
+    pub fn write(&mut self, address: u16, data: u8) -> u8 {
+        match address {
+            HDMA1 => self.source = (self.source & 0x00FF) | (data as u16) << 8,
+            HDMA2 => self.source = (self.source & 0xFF00) | (data & 0xF0) as u16,
+            HDMA3 => self.dest = (self.dest & 0x00FF) | ((data & 0x1F) as u16) << 8,
+            HDMA4 => self.dest = (self.dest & 0xFF00) | (data & 0xF0) as u16,
+            HDMA5 => {
+                let blocks = (data & 0x7F) + 1;
+                match (data & 0x80 != 0, self.hblank) {
+                    // Clearing bit 7 cancels an active HBlank transfer
+                    (false, true) => self.hblank = false,
+                    (false, false) => {
+                        self.remaining = blocks;
+                        return blocks;
+                    }
+                    (true, _) => {
+                        self.remaining = blocks;
+                        self.hblank = true;
+                    }
+                }
+            }
+            _ => {}
+        }
+        0
+    }
+
+    pub const fn hblank_active(&self) -> bool {
+        self.hblank
+    }
+
+    /// Source and VRAM destination of the next block, advancing past it
+    pub fn next_block(&mut self) -> Option<(u16, u16)> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let block = (self.source, 0x8000 | (self.dest & 0x1FF0));
+        self.source = self.source.wrapping_add(BLOCK_SIZE);
+        self.dest = self.dest.wrapping_add(BLOCK_SIZE) & 0x1FF0;
+        self.remaining -= 1;
+        if self.remaining == 0 {
+            self.hblank = false;
+        }
+        Some(block)
+    }
+}
@@ -1,6 +1,9 @@
 use bitflags::bitflags;
 
-use super::interrupts::{InterruptFlag, Interrupts};
+use super::{
+    interrupts::{InterruptFlag, Interrupts},
+    sgb::Sgb,
+};
 
 bitflags! {
     pub struct Buttons: u8 {
@@ -32,31 +35,75 @@ pub enum GbButton {
     Down,
 }
 
+impl GbButton {
+    pub const ALL: [Self; 8] = [
+        Self::A,
+        Self::B,
+        Self::Select,
+        Self::Start,
+        Self::Right,
+        Self::Left,
+        Self::Up,
+        Self::Down,
+    ];
+}
+
 const SELECT_DPAD: u8 = 1 << 4;
 const SELECT_BUTTONS: u8 = 1 << 5;
 
+/// Controllers that can be connected through the SGB multiplayer adapter
+pub const MAX_PLAYERS: usize = 4;
+
 pub struct Joypad {
-    buttons: Buttons,
-    dpad: DPad,
+    buttons: [Buttons; MAX_PLAYERS],
+    dpad: [DPad; MAX_PLAYERS],
     register: u8,
+    sgb: Option<Sgb>,
 }
 
 impl Joypad {
-    pub const fn new() -> Self {
+    /// With `sgb`, writes are also decoded as Super Game Boy command packets
+    pub fn new(sgb: bool) -> Self {
         Self {
-            buttons: Buttons::all(),
-            dpad: DPad::all(),
+            buttons: [Buttons::all(); MAX_PLAYERS],
+            dpad: [DPad::all(); MAX_PLAYERS],
             register: 0xCF,
+            sgb: sgb.then(Sgb::new),
+        }
+    }
+
+    pub fn sgb(&self) -> Option<&Sgb> {
+        self.sgb.as_ref()
+    }
+
+    pub fn sgb_mut(&mut self) -> Option<&mut Sgb> {
+        self.sgb.as_mut()
+    }
+
+    /// With SGB multiplayer enabled, the selected controller changes on P15
+    /// writes and its number is read back while no line is selected
+    pub fn read(&self) -> u8 {
+        let player = self.sgb.as_ref().map_or(0, Sgb::player);
+        let mut value = self.lines();
+
+        let multiplayer = self.sgb.as_ref().is_some_and(|sgb| sgb.players() > 1);
+        let unselected = SELECT_BUTTONS | SELECT_DPAD;
+        if multiplayer && self.register & unselected == unselected {
+            value = (value & 0xF0) | (0x0F - player as u8);
         }
+        value
     }
 
-    pub const fn read(&self) -> u8 {
-        let mut value = self.register & 0xF0;
+    /// P1 without the SGB controller number, P10-P13 are low for pressed
+    /// buttons in the selected groups
+    pub fn lines(&self) -> u8 {
+        let player = self.sgb.as_ref().map_or(0, Sgb::player);
+        let mut value = self.register | 0x0F;
         if self.register & SELECT_BUTTONS == 0 {
-            value |= self.buttons.bits;
+            value &= 0xF0 | self.buttons[player].bits;
         }
         if self.register & SELECT_DPAD == 0 {
-            value |= self.dpad.bits;
+            value &= 0xF0 | self.dpad[player].bits;
         }
         value
     }
@@ -64,31 +111,45 @@ impl Joypad {
     pub fn write(&mut self, data: u8) {
         self.register = data & 0x3F;
         self.register |= 0xC0;
+
+        if let Some(sgb) = &mut self.sgb {
+            sgb.write_joypad(data);
+        }
     }
 
-    pub fn keyup(&mut self, button: GbButton) {
+    pub fn keyup(&mut self, player: usize, button: GbButton) {
+        if player >= MAX_PLAYERS {
+            return;
+        }
+
+        let (buttons, dpad) = (&mut self.buttons[player], &mut self.dpad[player]);
         match button {
-            GbButton::A => self.buttons.insert(Buttons::A),
-            GbButton::B => self.buttons.insert(Buttons::B),
-            GbButton::Select => self.buttons.insert(Buttons::SELECT),
-            GbButton::Start => self.buttons.insert(Buttons::START),
-            GbButton::Right => self.dpad.insert(DPad::RIGHT),
-            GbButton::Left => self.dpad.insert(DPad::LEFT),
-            GbButton::Up => self.dpad.insert(DPad::UP),
-            GbButton::Down => self.dpad.insert(DPad::DOWN),
+            GbButton::A => buttons.insert(Buttons::A),
+            GbButton::B => buttons.insert(Buttons::B),
+            GbButton::Select => buttons.insert(Buttons::SELECT),
+            GbButton::Start => buttons.insert(Buttons::START),
+            GbButton::Right => dpad.insert(DPad::RIGHT),
+            GbButton::Left => dpad.insert(DPad::LEFT),
+            GbButton::Up => dpad.insert(DPad::UP),
+            GbButton::Down => dpad.insert(DPad::DOWN),
         }
     }
 
-    pub fn keydown(&mut self, button: GbButton, intr: &mut Interrupts) {
+    pub fn keydown(&mut self, player: usize, button: GbButton, intr: &mut Interrupts) {
+        if player >= MAX_PLAYERS {
+            return;
+        }
+
+        let (buttons, dpad) = (&mut self.buttons[player], &mut self.dpad[player]);
         match button {
-            GbButton::A => self.buttons.remove(Buttons::A),
-            GbButton::B => self.buttons.remove(Buttons::B),
-            GbButton::Select => self.buttons.remove(Buttons::SELECT),
-            GbButton::Start => self.buttons.remove(Buttons::START),
-            GbButton::Right => self.dpad.remove(DPad::RIGHT),
-            GbButton::Left => self.dpad.remove(DPad::LEFT),
-            GbButton::Up => self.dpad.remove(DPad::UP),
-            GbButton::Down => self.dpad.remove(DPad::DOWN),
+            GbButton::A => buttons.remove(Buttons::A),
+            GbButton::B => buttons.remove(Buttons::B),
+            GbButton::Select => buttons.remove(Buttons::SELECT),
+            GbButton::Start => buttons.remove(Buttons::START),
+            GbButton::Right => dpad.remove(DPad::RIGHT),
+            GbButton::Left => dpad.remove(DPad::LEFT),
+            GbButton::Up => dpad.remove(DPad::UP),
+            GbButton::Down => dpad.remove(DPad::DOWN),
         }
         intr.request(InterruptFlag::JOYPAD);
     }
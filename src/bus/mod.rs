@@ -1,18 +1,36 @@
-use crate::cpu;
+use crate::{cpu, model::Model};
 
 use self::{
-    cartridge::Cartridge,
+    boot::{BootRom, BootRomError},
+    cartridge::{
+        header::{CgbSupport, SgbSupport},
+        Cartridge,
+    },
+    cheats::{Cheat, CheatError, Cheats, GameShark},
+    compat::CompatPalette,
+    hdma::{Hdma, BLOCK_SIZE},
     interrupts::{InterruptFlag, Interrupts},
     joypad::{GbButton, Joypad},
+    ppu::{Mode, Ppu},
     ram::Ram,
+    sgb::Sgb,
     timer::Timer,
+    video::Video,
 };
 
+pub mod boot;
 pub mod cartridge;
+pub mod cheats;
+pub mod compat;
+mod hdma;
 pub mod interrupts;
 pub mod joypad;
+pub mod ppu;
 mod ram;
+pub mod search;
+pub mod sgb;
 mod timer;
+pub mod video;
 
 const ROM_START: u16 = 0x0000;
 const ROM_END: u16 = 0x7FFF;
@@ -20,8 +38,13 @@ const ROM_END: u16 = 0x7FFF;
 const VRAM_START: u16 = 0x8000;
 const VRAM_END: u16 = 0x9FFF;
 
+const EXT_RAM_START: u16 = 0xA000;
+const EXT_RAM_END: u16 = 0xBFFF;
+
 const WRAM_START: u16 = 0xC000;
 const WRAM_END: u16 = 0xFDFF;
+const WRAM_BANKED_START: u16 = 0xD000;
+const WRAM_BANKED_END: u16 = 0xDFFF;
 
 const JOYPAD: u16 = 0xFF00;
 
@@ -33,56 +56,271 @@ const TIMER_END: u16 = 0xFF07;
 
 const INTR_FLAG: u16 = 0xFF0F;
 
+const BOOT_ROM_DISABLE: u16 = 0xFF50;
+
+const OAM_START: u16 = 0xFE00;
+const OAM_END: u16 = 0xFE9F;
+
+const LCD_START: u16 = 0xFF40;
+const LCD_END: u16 = 0xFF4B;
+const OAM_DMA: u16 = 0xFF46;
+const KEY0: u16 = 0xFF4C;
+const KEY1: u16 = 0xFF4D;
+const VBK: u16 = 0xFF4F;
+const HDMA_START: u16 = 0xFF51;
+const HDMA_END: u16 = 0xFF55;
+const PALETTES_START: u16 = 0xFF68;
+const PALETTES_END: u16 = 0xFF6C;
+const SVBK: u16 = 0xFF70;
+
 const HRAM_START: u16 = 0xFF80;
 const HRAM_END: u16 = 0xFFFE;
 
 const INTR_ENABLE: u16 = 0xFFFF;
 
+/// Values of IF and SC after the boot ROM, unused bits read as 1 on hardware
+const POST_BOOT_IF: u8 = 0xE1;
+const POST_BOOT_SC_DMG: u8 = 0x7E;
+const POST_BOOT_SC_CGB: u8 = 0x7F;
+
+/// Dots taken by each 16-byte HDMA block, during which the CPU is halted
+const HDMA_BLOCK_DOTS: u32 = 32;
+
+/// M-cycles the CPU is paused for while switching speed
+const SPEED_SWITCH_CYCLES: usize = 2050;
+
 pub struct Bus {
+    model: Model,
     interrupts: Interrupts,
     cartridge: Cartridge,
+    boot_rom: Option<BootRom>,
     ram: Ram,
+    video: Video,
+    ppu: Ppu,
+    hdma: Hdma,
     joypad: Joypad,
     serial_data: [u8; 2],
     timer: Timer,
     cycles: u64,
+    speed_armed: bool,
+    double_speed: bool,
+    cheats: Cheats,
+    game_shark: Vec<GameShark>,
 }
 
 impl Bus {
-    pub fn new(cartridge: Cartridge) -> Self {
+    pub fn new(cartridge: Cartridge, model: Model) -> Self {
+        let mut interrupts = Interrupts::new();
+        interrupts.set_flags(POST_BOOT_IF);
+
+        let serial_ctrl = match model.is_cgb() {
+            true => POST_BOOT_SC_CGB,
+            false => POST_BOOT_SC_DMG,
+        };
+
+        let cgb_mode = model.is_cgb() && cartridge.header().cgb != CgbSupport::None;
+        let sgb_mode = model.is_sgb() && cartridge.header().sgb == SgbSupport::Supported;
+
+        let mut video = Video::new(cgb_mode);
+        if model.is_cgb() && !cgb_mode {
+            let header = cartridge.header();
+            video.set_compat_palette(&CompatPalette::from_title(header.title_hash, &header.title));
+        }
+
         Self {
-            interrupts: Interrupts::new(),
+            model,
+            interrupts,
             cartridge,
+            boot_rom: None,
             ram: Ram::new(),
-            joypad: Joypad::new(),
-            serial_data: [0; 2],
-            timer: Timer::new(),
+            video,
+            ppu: Ppu::new(model.is_cgb()),
+            hdma: Hdma::new(),
+            joypad: Joypad::new(sgb_mode),
+            serial_data: [0, serial_ctrl],
+            timer: Timer::new(model),
             cycles: 0,
+            speed_armed: false,
+            double_speed: false,
+            cheats: Cheats::new(),
+            game_shark: vec![],
+        }
+    }
+
+    /// Overlays `data` on the cartridge until $FF50 is written, restarting from power-on
+    pub fn load_boot_rom(&mut self, data: Vec<u8>) -> Result<(), BootRomError> {
+        self.boot_rom = Some(BootRom::new(data)?);
+        self.interrupts = Interrupts::new();
+        self.serial_data = [0; 2];
+        self.timer = Timer::power_on();
+        self.ppu = Ppu::power_on(self.model.is_cgb());
+        // The CGB boot ROM switches to DMG mode itself through KEY0
+        self.video.set_cgb_mode(self.model.is_cgb());
+        Ok(())
+    }
+
+    pub fn sgb(&self) -> Option<&Sgb> {
+        self.joypad.sgb()
+    }
+
+    pub fn sgb_mut(&mut self) -> Option<&mut Sgb> {
+        self.joypad.sgb_mut()
+    }
+
+    pub fn video(&self) -> &Video {
+        &self.video
+    }
+
+    pub fn ppu(&self) -> &Ppu {
+        &self.ppu
+    }
+
+    /// Returns whether a frame was completed since the last call
+    pub fn take_frame(&mut self) -> bool {
+        self.ppu.take_frame()
+    }
+
+    /// Overrides the palette of a DMG game running on CGB, returns false
+    /// for other games and models
+    pub fn set_compat_palette(&mut self, palette: &CompatPalette) -> bool {
+        let compat = self.model.is_cgb() && !self.video.cgb_mode();
+        if compat {
+            self.video.set_compat_palette(palette);
         }
+        compat
+    }
+
+    pub fn cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    pub fn cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    pub fn rumble(&self) -> bool {
+        self.cartridge.rumble()
+    }
+
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        self.cartridge.set_tilt(x, y);
+    }
+
+    pub fn has_accelerometer(&self) -> bool {
+        self.cartridge.has_accelerometer()
+    }
+
+    pub fn cheats(&self) -> &[Cheat] {
+        self.cheats.list()
     }
 
-    pub fn keyup(&mut self, button: GbButton) {
-        self.joypad.keyup(button);
+    /// Returns the skipped invalid lines, see `Cheats::load`
+    pub fn load_cheats(&mut self, text: &str) -> Vec<(usize, CheatError)> {
+        let errors = self.cheats.load(text);
+        self.update_cheats();
+        errors
     }
 
-    pub fn keydown(&mut self, button: GbButton) {
-        self.joypad.keydown(button, &mut self.interrupts);
+    pub fn add_cheat(&mut self, code: &str) -> Result<(), CheatError> {
+        self.cheats.add(Cheat::parse(code)?);
+        self.update_cheats();
+        Ok(())
+    }
+
+    pub fn toggle_cheat(&mut self, index: usize) -> Option<bool> {
+        let enabled = self.cheats.toggle(index);
+        self.update_cheats();
+        enabled
+    }
+
+    fn apply_game_shark(&mut self, code: GameShark) {
+        let GameShark {
+            bank,
+            address,
+            data,
+        } = code;
+        match (bank, address) {
+            (Some(bank), EXT_RAM_START..=EXT_RAM_END) => {
+                self.cartridge.write_ram_bank(bank, address, data)
+            }
+            (Some(bank), WRAM_BANKED_START..=WRAM_BANKED_END) => {
+                self.ram.wram_bank_write(bank, address, data)
+            }
+            _ => cpu::Interface::set(self, address, data),
+        }
+    }
+
+    /// Copies $XX00-$XX9F to OAM, instantly rather than over 160 cycles
+    fn oam_dma(&mut self, page: u8) {
+        let source = (page as u16) << 8;
+        for i in 0..=(OAM_END - OAM_START) {
+            let data = cpu::Interface::peek(self, source + i);
+            self.video.oam_write(OAM_START + i, data);
+        }
+    }
+
+    /// Copies the next HDMA block into VRAM, stalling the CPU meanwhile
+    fn hdma_block(&mut self) {
+        if let Some((source, dest)) = self.hdma.next_block() {
+            for i in 0..BLOCK_SIZE {
+                let data = cpu::Interface::peek(self, source.wrapping_add(i));
+                self.video.vram_write(dest + i, data);
+            }
+
+            let dots_per_cycle = self.dots_per_cycle();
+            cpu::Interface::tick(self, (HDMA_BLOCK_DOTS / dots_per_cycle) as usize);
+        }
+    }
+
+    const fn dots_per_cycle(&self) -> u32 {
+        match self.double_speed {
+            true => 2,
+            false => 4,
+        }
+    }
+
+    fn update_cheats(&mut self) {
+        self.cartridge.set_game_genie(self.cheats.game_genie());
+        self.game_shark = self.cheats.game_shark();
+    }
+
+    /// `player` is 0 unless several controllers are used through SGB MLT_REQ
+    pub fn keyup(&mut self, player: usize, button: GbButton) {
+        self.joypad.keyup(player, button);
+    }
+
+    pub fn keydown(&mut self, player: usize, button: GbButton) {
+        self.joypad.keydown(player, button, &mut self.interrupts);
     }
 }
 
 impl cpu::Interface for Bus {
     fn peek(&self, address: u16) -> u8 {
+        if let Some(data) = self.boot_rom.as_ref().and_then(|boot| boot.read(address)) {
+            return data;
+        }
+
         match address {
             ROM_START..=ROM_END => self.cartridge.read(address),
+            VRAM_START..=VRAM_END => self.video.vram_read(address),
+            EXT_RAM_START..=EXT_RAM_END => self.cartridge.read(address),
             WRAM_START..=WRAM_END => self.ram.wram_read(address),
+            OAM_START..=OAM_END => self.video.oam_read(address),
             JOYPAD => self.joypad.read(),
             SERIAL_DATA => self.serial_data[0],
             SERIAL_CTRL => self.serial_data[1],
             TIMER_START..=TIMER_END => self.timer.read(address),
             INTR_FLAG => self.interrupts.flags(),
+            BOOT_ROM_DISABLE => 0xFF,
+            VBK | PALETTES_START..=PALETTES_END => self.video.read(address),
+            HDMA_START..=HDMA_END if self.video.cgb_mode() => self.hdma.read(address),
+            SVBK if self.video.cgb_mode() => self.ram.wram_bank(),
+            KEY1 if self.video.cgb_mode() => {
+                0x7E | (self.double_speed as u8) << 7 | self.speed_armed as u8
+            }
             HRAM_START..=HRAM_END => self.ram.hram_read(address),
             INTR_ENABLE => self.interrupts.get_enable(),
-            0xFF44 => 0x94,
+            LCD_START..=LCD_END => self.ppu.read(address),
             _ => 0,
         }
     }
@@ -90,14 +328,45 @@ impl cpu::Interface for Bus {
     fn set(&mut self, address: u16, data: u8) {
         match address {
             ROM_START..=ROM_END => self.cartridge.write(address, data),
+            VRAM_START..=VRAM_END => self.video.vram_write(address, data),
+            EXT_RAM_START..=EXT_RAM_END => self.cartridge.write(address, data),
             WRAM_START..=WRAM_END => self.ram.wram_write(address, data),
+            OAM_START..=OAM_END => self.video.oam_write(address, data),
             JOYPAD => self.joypad.write(data),
             SERIAL_DATA => self.serial_data[0] = data,
             SERIAL_CTRL => self.serial_data[1] = data,
             TIMER_START..=TIMER_END => self.timer.write(address, data),
             INTR_FLAG => self.interrupts.set_flags(data),
+            BOOT_ROM_DISABLE if data != 0 => self.boot_rom = None,
+            OAM_DMA => self.oam_dma(data),
+            KEY0 if self.boot_rom.is_some() && self.model.is_cgb() => {
+                self.video.set_cgb_mode(data & 0x04 == 0)
+            }
+            VBK | PALETTES_START..=PALETTES_END => self.video.write(address, data),
+            HDMA_START..=HDMA_END if self.video.cgb_mode() => {
+                let active = self.hdma.hblank_active();
+                let blocks = self.hdma.write(address, data);
+                for _ in 0..blocks {
+                    self.hdma_block();
+                }
+                // An HBlank transfer started in HBlank or with the LCD off
+                // copies its first block right away
+                if !active && self.hdma.hblank_active() && self.ppu.mode() == Mode::HBlank {
+                    self.hdma_block();
+                }
+            }
+            SVBK if self.video.cgb_mode() => self.ram.set_wram_bank(data),
+            KEY1 if self.video.cgb_mode() => self.speed_armed = data & 0x01 != 0,
             HRAM_START..=HRAM_END => self.ram.hram_write(address, data),
             INTR_ENABLE => self.interrupts.set_enable(data),
+            LCD_START..=LCD_END => {
+                let hblank = self.ppu.mode() == Mode::HBlank;
+                self.ppu.write(address, data);
+                // Turning the LCD off mid-frame enters HBlank
+                if !hblank && self.ppu.mode() == Mode::HBlank && self.hdma.hblank_active() {
+                    self.hdma_block();
+                }
+            }
             _ => {}
         };
     }
@@ -108,9 +377,42 @@ impl cpu::Interface for Bus {
             for _ in 0..4 {
                 self.timer.tick(&mut self.interrupts);
             }
+
+            let dots = self.dots_per_cycle();
+            match self.ppu.tick(dots, &self.video, &mut self.interrupts) {
+                Some(Mode::HBlank) if self.hdma.hblank_active() => self.hdma_block(),
+                Some(Mode::VBlank) => {
+                    if let Some(sgb) = self.joypad.sgb_mut() {
+                        sgb.end_frame(self.ppu.shades());
+                    }
+                    for i in 0..self.game_shark.len() {
+                        self.apply_game_shark(self.game_shark[i]);
+                    }
+                }
+                _ => {}
+            }
         }
     }
 
+    fn stop(&mut self) -> bool {
+        // STOP resets DIV
+        self.timer.write(TIMER_START, 0);
+
+        match self.video.cgb_mode() && self.speed_armed {
+            true => {
+                self.double_speed = !self.double_speed;
+                self.speed_armed = false;
+                self.tick(SPEED_SWITCH_CYCLES);
+                false
+            }
+            false => true,
+        }
+    }
+
+    fn joypad_input(&self) -> bool {
+        self.joypad.lines() & 0x0F != 0x0F
+    }
+
     fn cycles(&self) -> u64 {
         self.cycles
     }
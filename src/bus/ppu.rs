@@ -0,0 +1,477 @@
+use super::{
+    interrupts::{InterruptFlag, Interrupts},
+    video::{TileAttributes, Video},
+};
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+const DOTS_PER_LINE: u32 = 456;
+const LINES_PER_FRAME: u8 = 154;
+const DOTS_PER_FRAME: u32 = DOTS_PER_LINE * LINES_PER_FRAME as u32;
+/// Length of the OAM scan at the start of each visible line
+const OAM_SCAN_DOTS: u32 = 80;
+/// Dot of a visible line at which HBlank starts, assuming the shortest mode 3
+const HBLANK_START: u32 = 252;
+
+const LCDC: u16 = 0xFF40;
+const STAT: u16 = 0xFF41;
+const SCY: u16 = 0xFF42;
+const SCX: u16 = 0xFF43;
+const LY: u16 = 0xFF44;
+const LYC: u16 = 0xFF45;
+const BGP: u16 = 0xFF47;
+const OBP0: u16 = 0xFF48;
+const OBP1: u16 = 0xFF49;
+const WY: u16 = 0xFF4A;
+const WX: u16 = 0xFF4B;
+
+const LCD_ENABLE: u8 = 1 << 7;
+const WINDOW_MAP: u8 = 1 << 6;
+const WINDOW_ENABLE: u8 = 1 << 5;
+const TILE_DATA: u8 = 1 << 4;
+const BG_MAP: u8 = 1 << 3;
+const OBJ_SIZE: u8 = 1 << 2;
+const OBJ_ENABLE: u8 = 1 << 1;
+const BG_ENABLE: u8 = 1 << 0;
+
+const LYC_INTERRUPT: u8 = 1 << 6;
+const OAM_INTERRUPT: u8 = 1 << 5;
+const VBLANK_INTERRUPT: u8 = 1 << 4;
+const HBLANK_INTERRUPT: u8 = 1 << 3;
+const LYC_EQUAL: u8 = 1 << 2;
+const STAT_WRITABLE: u8 = 0x78;
+
+const MAP_LOW: u16 = 0x9800;
+const MAP_HIGH: u16 = 0x9C00;
+const TILES_UNSIGNED: u16 = 0x8000;
+const TILES_SIGNED: u16 = 0x9000;
+const TILE_SIZE: u16 = 16;
+
+/// Window X position is offset by 7 pixels
+const WX_OFFSET: i16 = 7;
+
+const POST_BOOT_LCDC: u8 = 0x91;
+const POST_BOOT_BGP: u8 = 0xFC;
+const POST_BOOT_OBP: u8 = 0xFF;
+
+/// BGR555 grey levels of the four DMG shades, lightest first
+const DMG_COLORS: [u16; 4] = [0x7FFF, 0x56B5, 0x294A, 0x0000];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    HBlank = 0,
+    VBlank = 1,
+    OamScan = 2,
+    Drawing = 3,
+}
+
+/// LCD registers, mode timing and a scanline renderer drawing from `Video`.
+/// Mode 3 always takes its shortest length and each line is drawn at once
+/// when it ends.
+pub struct Ppu {
+    lcdc: u8,
+    stat: u8,
+    scy: u8,
+    scx: u8,
+    ly: u8,
+    lyc: u8,
+    bgp: u8,
+    obp: [u8; 2],
+    wy: u8,
+    wx: u8,
+    /// Dot within the line, or within the blank frame while the LCD is off
+    dot: u32,
+    mode: Mode,
+    /// Internal line counter of the window, only advanced on lines showing it
+    window_line: u8,
+    /// STAT interrupt line, the interrupt fires on its rising edge
+    stat_line: bool,
+    /// DMG games on CGB are colored through the CGB palettes
+    cgb_model: bool,
+    shades: Vec<u8>,
+    frame: Vec<u16>,
+    frame_ready: bool,
+}
+
+impl Ppu {
+    /// LCD state left by the boot ROM
+    pub fn new(cgb_model: bool) -> Self {
+        Self {
+            lcdc: POST_BOOT_LCDC,
+            bgp: POST_BOOT_BGP,
+            obp: [POST_BOOT_OBP; 2],
+            ..Self::power_on(cgb_model)
+        }
+    }
+
+    /// LCD state at power-on, with the display off
+    pub fn power_on(cgb_model: bool) -> Self {
+        Self {
+            lcdc: 0,
+            stat: 0,
+            scy: 0,
+            scx: 0,
+            ly: 0,
+            lyc: 0,
+            bgp: 0,
+            obp: [0; 2],
+            wy: 0,
+            wx: 0,
+            dot: 0,
+            mode: Mode::HBlank,
+            window_line: 0,
+            stat_line: false,
+            cgb_model,
+            shades: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            frame: vec![DMG_COLORS[0]; SCREEN_WIDTH * SCREEN_HEIGHT],
+            frame_ready: false,
+        }
+    }
+
+    pub const fn read(&self, address: u16) -> u8 {
+        match address {
+            LCDC => self.lcdc,
+            STAT => {
+                let lyc_equal = match self.ly == self.lyc {
+                    true => LYC_EQUAL,
+                    false => 0,
+                };
+                0x80 | self.stat | lyc_equal | self.mode as u8
+            }
+            SCY => self.scy,
+            SCX => self.scx,
+            LY => self.ly,
+            LYC => self.lyc,
+            BGP => self.bgp,
+            OBP0 => self.obp[0],
+            OBP1 => self.obp[1],
+            WY => self.wy,
+            WX => self.wx,
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write(&mut self, address: u16, data: u8) {
+        match address {
+            LCDC => {
+                if (data ^ self.lcdc) & LCD_ENABLE != 0 {
+                    self.ly = 0;
+                    self.dot = 0;
+                    self.mode = Mode::HBlank;
+                    self.window_line = 0;
+                    self.stat_line = false;
+                }
+                self.lcdc = data;
+            }
+            STAT => self.stat = data & STAT_WRITABLE,
+            SCY => self.scy = data,
+            SCX => self.scx = data,
+            LYC => self.lyc = data,
+            BGP => self.bgp = data,
+            OBP0 => self.obp[0] = data,
+            OBP1 => self.obp[1] = data,
+            WY => self.wy = data,
+            WX => self.wx = data,
+            _ => {}
+        }
+    }
+
+    /// Advances by `dots` and returns the last mode entered, if any. With the
+    /// LCD off, a blank frame still ends every 70224 dots and is reported as
+    /// VBlank, without the interrupt.
+    pub fn tick(&mut self, dots: u32, video: &Video, interrupts: &mut Interrupts) -> Option<Mode> {
+        if self.lcdc & LCD_ENABLE == 0 {
+            self.dot += dots;
+            if self.dot < DOTS_PER_FRAME {
+                return None;
+            }
+            self.dot -= DOTS_PER_FRAME;
+            self.shades.fill(0);
+            self.frame.fill(DMG_COLORS[0]);
+            self.frame_ready = true;
+            return Some(Mode::VBlank);
+        }
+
+        let mut entered = None;
+        for _ in 0..dots {
+            if let Some(mode) = self.step(video, interrupts) {
+                entered = Some(mode);
+            }
+        }
+        entered
+    }
+
+    fn step(&mut self, video: &Video, interrupts: &mut Interrupts) -> Option<Mode> {
+        self.dot += 1;
+        if self.dot == DOTS_PER_LINE {
+            self.dot = 0;
+            self.ly = (self.ly + 1) % LINES_PER_FRAME;
+            if self.ly == 0 {
+                self.window_line = 0;
+            }
+        }
+
+        let mode = match (self.ly as usize >= SCREEN_HEIGHT, self.dot) {
+            (true, _) => Mode::VBlank,
+            (false, 0..OAM_SCAN_DOTS) => Mode::OamScan,
+            (false, OAM_SCAN_DOTS..HBLANK_START) => Mode::Drawing,
+            (false, _) => Mode::HBlank,
+        };
+
+        let entered = match mode != self.mode {
+            true => Some(mode),
+            false => None,
+        };
+        if entered.is_some() {
+            self.mode = mode;
+            match mode {
+                Mode::HBlank => self.render_line(video),
+                Mode::VBlank => {
+                    interrupts.request(InterruptFlag::VBLANK);
+                    self.frame_ready = true;
+                }
+                _ => {}
+            }
+        }
+
+        self.update_stat(interrupts);
+        entered
+    }
+
+    fn update_stat(&mut self, interrupts: &mut Interrupts) {
+        let source = match self.mode {
+            Mode::HBlank => HBLANK_INTERRUPT,
+            Mode::VBlank => VBLANK_INTERRUPT,
+            Mode::OamScan => OAM_INTERRUPT,
+            Mode::Drawing => 0,
+        };
+        let line =
+            self.stat & source != 0 || (self.stat & LYC_INTERRUPT != 0 && self.ly == self.lyc);
+
+        if line && !self.stat_line {
+            interrupts.request(InterruptFlag::STAT);
+        }
+        self.stat_line = line;
+    }
+
+    /// Current mode, HBlank while the LCD is off
+    pub const fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Returns whether a frame was completed since the last call
+    pub fn take_frame(&mut self) -> bool {
+        std::mem::take(&mut self.frame_ready)
+    }
+
+    /// Last frame as DMG shades after BGP/OBP mapping, as seen by the SGB
+    pub fn shades(&self) -> &[u8] {
+        &self.shades
+    }
+
+    /// Last frame in BGR555
+    pub fn frame(&self) -> &[u16] {
+        &self.frame
+    }
+
+    const fn tile_address(&self, tile: u8) -> u16 {
+        match self.lcdc & TILE_DATA != 0 {
+            true => TILES_UNSIGNED + tile as u16 * TILE_SIZE,
+            false => TILES_SIGNED.wrapping_add((tile as i8 as i16 * TILE_SIZE as i16) as u16),
+        }
+    }
+
+    /// Color index of pixel (`x`, `y`) of the tile at `address`, flips applied
+    fn tile_pixel(video: &Video, bank: u8, address: u16, x: u8, y: u8, flip: (bool, bool)) -> u8 {
+        let x = match flip.0 {
+            true => 7 - x,
+            false => x,
+        };
+        let y = match flip.1 {
+            true => 7 - y,
+            false => y,
+        };
+
+        let vram = video.vram(bank as usize);
+        let offset = (address as usize + y as usize * 2) & (vram.len() - 1);
+        let bit = 7 - x;
+        let lo = (vram[offset] >> bit) & 0x01;
+        let hi = (vram[offset + 1] >> bit) & 0x01;
+        (hi << 1) | lo
+    }
+
+    const fn dmg_shade(palette: u8, color: u8) -> u8 {
+        (palette >> (color * 2)) & 0x03
+    }
+
+    fn render_line(&mut self, video: &Video) {
+        let cgb = video.cgb_mode();
+        let bg_enabled = self.lcdc & BG_ENABLE != 0;
+        let start = self.ly as usize * SCREEN_WIDTH;
+
+        let mut bg_colors = [0u8; SCREEN_WIDTH];
+        let mut bg_attributes = [TileAttributes::from_bits(0); SCREEN_WIDTH];
+
+        // In DMG mode LCDC bit 0 blanks the background and window, in CGB
+        // mode it only takes their priority away
+        if bg_enabled || cgb {
+            let window_x = self.wx as i16 - WX_OFFSET;
+            let window = self.lcdc & WINDOW_ENABLE != 0
+                && self.ly >= self.wy
+                && window_x < SCREEN_WIDTH as i16;
+
+            for x in 0..SCREEN_WIDTH {
+                let in_window = window && x as i16 >= window_x;
+                let (map, px, py) = match in_window {
+                    true => (
+                        self.map(WINDOW_MAP),
+                        (x as i16 - window_x) as u8,
+                        self.window_line,
+                    ),
+                    false => (
+                        self.map(BG_MAP),
+                        (x as u8).wrapping_add(self.scx),
+                        self.ly.wrapping_add(self.scy),
+                    ),
+                };
+
+                let entry = map + (py as u16 / 8) * 32 + px as u16 / 8;
+                let tile = video.vram(0)[(entry - TILES_UNSIGNED) as usize];
+                let attributes = video.tile_attributes(entry);
+                let flip = (attributes.x_flip, attributes.y_flip);
+                let color = Self::tile_pixel(
+                    video,
+                    attributes.bank,
+                    self.tile_address(tile),
+                    px % 8,
+                    py % 8,
+                    flip,
+                );
+
+                bg_colors[x] = color;
+                bg_attributes[x] = attributes;
+                self.draw_bg(video, start + x, attributes.palette, color);
+            }
+
+            if window {
+                self.window_line += 1;
+            }
+        } else {
+            for x in 0..SCREEN_WIDTH {
+                self.draw_bg(video, start + x, 0, 0);
+            }
+        }
+
+        if self.lcdc & OBJ_ENABLE != 0 {
+            self.render_objects(video, &bg_colors, &bg_attributes, bg_enabled);
+        }
+    }
+
+    fn render_objects(
+        &mut self,
+        video: &Video,
+        bg_colors: &[u8; SCREEN_WIDTH],
+        bg_attributes: &[TileAttributes; SCREEN_WIDTH],
+        bg_enabled: bool,
+    ) {
+        let tall = self.lcdc & OBJ_SIZE != 0;
+        let height: i16 = match tall {
+            true => 16,
+            false => 8,
+        };
+        let objects = video.line_objects(self.ly, tall);
+        let start = self.ly as usize * SCREEN_WIDTH;
+
+        for x in 0..SCREEN_WIDTH {
+            // The first opaque pixel wins, even when hidden by the background
+            for object in &objects {
+                let col = x as i16 + 8 - object.x as i16;
+                if !(0..8).contains(&col) {
+                    continue;
+                }
+
+                let attributes = object.attributes;
+                let mut row = self.ly as i16 + 16 - object.y as i16;
+                if attributes.y_flip {
+                    row = height - 1 - row;
+                }
+                let tile = match tall {
+                    true => object.tile & 0xFE,
+                    false => object.tile,
+                };
+                // Rows 8-15 of tall objects fall into the next tile
+                let address =
+                    TILES_UNSIGNED + tile as u16 * TILE_SIZE + (row as u16 / 8) * TILE_SIZE;
+                let bank = match video.cgb_mode() {
+                    true => attributes.bank,
+                    false => 0,
+                };
+                let color = Self::tile_pixel(
+                    video,
+                    bank,
+                    address,
+                    col as u8,
+                    row as u8 % 8,
+                    (attributes.x_flip, false),
+                );
+                if color == 0 {
+                    continue;
+                }
+
+                if video.object_visible(object, bg_attributes[x], bg_colors[x], bg_enabled) {
+                    self.draw_object(video, start + x, attributes, color);
+                }
+                break;
+            }
+        }
+    }
+
+    fn draw_bg(&mut self, video: &Video, i: usize, palette: u8, color: u8) {
+        let bg_enabled = self.lcdc & BG_ENABLE != 0;
+        let (shade, rgb) = match video.cgb_mode() {
+            true => (color, video.bg_palettes().color(palette, color)),
+            false => {
+                let shade = match bg_enabled {
+                    true => Self::dmg_shade(self.bgp, color),
+                    false => 0,
+                };
+                (
+                    shade,
+                    self.dmg_color(video.bg_palettes().color(0, shade), shade),
+                )
+            }
+        };
+        self.shades[i] = shade;
+        self.frame[i] = rgb;
+    }
+
+    fn draw_object(&mut self, video: &Video, i: usize, attributes: TileAttributes, color: u8) {
+        let (shade, rgb) = match video.cgb_mode() {
+            true => (color, video.obj_palettes().color(attributes.palette, color)),
+            false => {
+                let palette = attributes.dmg_palette;
+                let shade = Self::dmg_shade(self.obp[palette as usize], color);
+                let compat = video.obj_palettes().color(palette, shade);
+                (shade, self.dmg_color(compat, shade))
+            }
+        };
+        self.shades[i] = shade;
+        self.frame[i] = rgb;
+    }
+
+    /// Color of a DMG shade, taken from the compatibility palette on CGB
+    const fn dmg_color(&self, compat: u16, shade: u8) -> u16 {
+        match self.cgb_model {
+            true => compat,
+            false => DMG_COLORS[shade as usize],
+        }
+    }
+
+    const fn map(&self, select: u8) -> u16 {
+        match self.lcdc & select != 0 {
+            true => MAP_HIGH,
+            false => MAP_LOW,
+        }
+    }
+}
@@ -1,28 +1,61 @@
-const WRAM_SIZE: usize = 0x2000;
-const WRAM_MASK: usize = WRAM_SIZE - 1;
+const WRAM_BANK_SIZE: usize = 0x1000;
+const WRAM_BANKS: usize = 8;
+const WRAM_MASK: usize = 0x2000 - 1;
 
 const HRAM_SIZE: usize = 0x80;
 const HRAM_MASK: usize = HRAM_SIZE - 1;
 
 pub struct Ram {
-    wram: [u8; WRAM_SIZE],
+    wram: [u8; WRAM_BANK_SIZE * WRAM_BANKS],
+    /// Bank mapped at $D000-$DFFF, only switchable in CGB mode
+    wram_bank: usize,
     hram: [u8; HRAM_SIZE],
 }
 
 impl Ram {
     pub const fn new() -> Self {
         Self {
-            wram: [0; WRAM_SIZE],
+            wram: [0; WRAM_BANK_SIZE * WRAM_BANKS],
+            wram_bank: 1,
             hram: [0; HRAM_SIZE],
         }
     }
 
+    /// Offset of `address` with `bank` mapped at $D000-$DFFF
+    const fn wram_offset(bank: usize, address: u16) -> usize {
+        let offset = address as usize & WRAM_MASK;
+        match offset < WRAM_BANK_SIZE {
+            true => offset,
+            false => bank * WRAM_BANK_SIZE + (offset - WRAM_BANK_SIZE),
+        }
+    }
+
     pub const fn wram_read(&self, address: u16) -> u8 {
-        self.wram[(address as usize) & WRAM_MASK]
+        self.wram[Self::wram_offset(self.wram_bank, address)]
     }
 
     pub fn wram_write(&mut self, address: u16, data: u8) {
-        self.wram[(address as usize) & WRAM_MASK] = data;
+        self.wram[Self::wram_offset(self.wram_bank, address)] = data;
+    }
+
+    /// Writes through `bank` instead of the mapped bank, bank 0 selects bank 1
+    pub fn wram_bank_write(&mut self, bank: u8, address: u16, data: u8) {
+        let bank = (bank as usize & (WRAM_BANKS - 1)).max(1);
+        self.wram[Self::wram_offset(bank, address)] = data;
+    }
+
+    /// All eight banks back to back, bank n at n * $1000
+    pub const fn wram(&self) -> &[u8] {
+        &self.wram
+    }
+
+    /// SVBK, bank 0 selects bank 1
+    pub const fn wram_bank(&self) -> u8 {
+        0xF8 | self.wram_bank as u8
+    }
+
+    pub fn set_wram_bank(&mut self, data: u8) {
+        self.wram_bank = (data as usize & (WRAM_BANKS - 1)).max(1);
     }
 
     pub const fn hram_read(&self, address: u16) -> u8 {
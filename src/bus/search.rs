@@ -0,0 +1,153 @@
+use crate::cpu::Interface;
+
+use super::Bus;
+
+const SRAM_START: u16 = 0xA000;
+const SRAM_END: u16 = 0xBFFF;
+const SRAM_BANK_SIZE: usize = 0x2000;
+
+const WRAM_START: u16 = 0xC000;
+const WRAM_BANKED_START: u16 = 0xD000;
+const WRAM_BANKED_END: u16 = 0xDFFF;
+const WRAM_BANK_SIZE: usize = 0x1000;
+/// DMG mode only has banks 0 and 1
+const DMG_WRAM_BANKS: usize = 2;
+const CGB_WRAM_BANKS: usize = 8;
+
+const HRAM_START: u16 = 0xFF80;
+const HRAM_END: u16 = 0xFFFE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchFilter {
+    Equal,
+    Changed,
+    Increased,
+    Decreased,
+    Value(u8),
+}
+
+impl SearchFilter {
+    const fn matches(self, old: u8, new: u8) -> bool {
+        match self {
+            SearchFilter::Equal => new == old,
+            SearchFilter::Changed => new != old,
+            SearchFilter::Increased => new > old,
+            SearchFilter::Decreased => new < old,
+            SearchFilter::Value(value) => new == value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Candidate {
+    /// Cartridge RAM or WRAM bank holding the byte, 0 when unbanked
+    pub bank: u8,
+    pub address: u16,
+    pub value: u8,
+}
+
+impl Candidate {
+    /// Byte `offset` of the whole cartridge RAM
+    const fn sram(offset: usize) -> Self {
+        Self {
+            bank: (offset / SRAM_BANK_SIZE) as u8,
+            address: SRAM_START + (offset % SRAM_BANK_SIZE) as u16,
+            value: 0,
+        }
+    }
+
+    /// Byte `offset` of the eight WRAM banks, bank 0 is the one at $C000-$CFFF
+    const fn wram(offset: usize) -> Self {
+        let bank = offset / WRAM_BANK_SIZE;
+        let start = match bank {
+            0 => WRAM_START,
+            _ => WRAM_BANKED_START,
+        };
+        Self {
+            bank: bank as u8,
+            address: start + (offset % WRAM_BANK_SIZE) as u16,
+            value: 0,
+        }
+    }
+
+    const fn hram(address: u16) -> Self {
+        Self {
+            bank: 0,
+            address,
+            value: 0,
+        }
+    }
+
+    /// Reads the byte from its own bank, whichever bank is mapped
+    fn read(&self, bus: &Bus) -> u8 {
+        let bank = self.bank as usize;
+        match self.address {
+            SRAM_START..=SRAM_END => {
+                let offset = bank * SRAM_BANK_SIZE + (self.address - SRAM_START) as usize;
+                bus.cartridge.ram().get(offset).copied().unwrap_or(0xFF)
+            }
+            WRAM_START..=WRAM_BANKED_END => {
+                let offset = bank * WRAM_BANK_SIZE + (self.address as usize % WRAM_BANK_SIZE);
+                bus.ram.wram()[offset]
+            }
+            _ => bus.peek(self.address),
+        }
+    }
+
+    /// GameShark code that keeps this byte at `value`, selecting its bank
+    /// when it lives in banked RAM
+    pub fn game_shark(&self, value: u8) -> String {
+        let code = match self.address {
+            SRAM_START..=SRAM_END => 0x80 | self.bank,
+            WRAM_BANKED_START..=WRAM_BANKED_END => 0x90 | self.bank,
+            _ => 0x01,
+        };
+        let [lo, hi] = self.address.to_le_bytes();
+        format!("{:02X}{:02X}{:02X}{:02X}", code, value, lo, hi)
+    }
+}
+
+/// RAM scanner narrowing down addresses by comparing snapshots taken between
+/// frames. Every WRAM and cartridge RAM bank is searched, not only the mapped ones.
+pub struct CheatSearch {
+    candidates: Vec<Candidate>,
+}
+
+impl CheatSearch {
+    pub fn new(bus: &Bus) -> Self {
+        let wram_banks = match bus.video.cgb_mode() {
+            true => CGB_WRAM_BANKS,
+            false => DMG_WRAM_BANKS,
+        };
+        let sram = (0..bus.cartridge.ram().len()).map(Candidate::sram);
+        let wram = (0..wram_banks * WRAM_BANK_SIZE).map(Candidate::wram);
+        let hram = (HRAM_START..=HRAM_END).map(Candidate::hram);
+
+        let candidates = wram
+            .chain(hram)
+            .chain(sram)
+            .map(|candidate| Candidate {
+                value: candidate.read(bus),
+                ..candidate
+            })
+            .collect();
+
+        Self { candidates }
+    }
+
+    /// Keeps the candidates matching `filter` against the previous snapshot
+    /// and returns how many are left
+    pub fn filter(&mut self, bus: &Bus, filter: SearchFilter) -> usize {
+        self.candidates.retain_mut(|candidate| {
+            let value = candidate.read(bus);
+            let keep = filter.matches(candidate.value, value);
+            candidate.value = value;
+            keep
+        });
+        self.candidates.len()
+    }
+
+    pub fn candidates(&self) -> &[Candidate] {
+        &self.candidates
+    }
+}
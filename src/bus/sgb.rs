@@ -0,0 +1,538 @@
+use super::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+const PACKET_SIZE: usize = 16;
+const PACKET_BITS: usize = PACKET_SIZE * 8;
+const MAX_PACKETS: usize = 7;
+
+const CELLS_X: usize = SCREEN_WIDTH / 8;
+const CELLS_Y: usize = SCREEN_HEIGHT / 8;
+const CELLS: usize = CELLS_X * CELLS_Y;
+
+const SYSTEM_PALETTES: usize = 512;
+const ATTR_FILES: usize = 45;
+/// An attribute file packs the 20x18 cells at 2 bits each
+const ATTR_FILE_SIZE: usize = CELLS / 4;
+
+pub const BORDER_WIDTH: usize = 256;
+pub const BORDER_HEIGHT: usize = 224;
+/// Position of the game screen inside the border
+const GAME_X: usize = 48;
+const GAME_Y: usize = 40;
+
+/// Border tiles are SNES 4bpp, 128 are sent by each CHR_TRN
+const BORDER_TILE_SIZE: usize = 32;
+const BORDER_TILES: usize = 256;
+const BORDER_MAP_WIDTH: usize = 32;
+/// PCT_TRN data: the 32x32 tile map followed by palettes 4-7
+const BORDER_MAP_SIZE: usize = BORDER_MAP_WIDTH * 32 * 2;
+const BORDER_PALETTES: usize = 4;
+
+/// Size of a VRAM transfer, the first 256 tiles shown on screen
+const TRANSFER_SIZE: usize = 0x1000;
+/// Frames the SGB waits after a transfer command before reading the screen
+const TRANSFER_DELAY: u8 = 3;
+
+const PAL01: u8 = 0x00;
+const PAL23: u8 = 0x01;
+const PAL03: u8 = 0x02;
+const PAL12: u8 = 0x03;
+const ATTR_BLK: u8 = 0x04;
+const ATTR_LIN: u8 = 0x05;
+const ATTR_DIV: u8 = 0x06;
+const ATTR_CHR: u8 = 0x07;
+const PAL_SET: u8 = 0x0A;
+const PAL_TRN: u8 = 0x0B;
+const CHR_TRN: u8 = 0x13;
+const PCT_TRN: u8 = 0x14;
+const ATTR_TRN: u8 = 0x15;
+const ATTR_SET: u8 = 0x16;
+const MLT_REQ: u8 = 0x11;
+const MASK_EN: u8 = 0x17;
+
+/// Palette 1-A, shown until the game sets its own
+const DEFAULT_PALETTE: [u16; 4] = [0x67BF, 0x265B, 0x10B5, 0x2866];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transfer {
+    Palettes,
+    Attributes,
+    BorderTiles { high: bool },
+    Border,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mask {
+    None,
+    /// Keeps showing the last frame
+    Freeze,
+    Black,
+    /// Fills the screen with color 0
+    Color0,
+}
+
+/// Super Game Boy command receiver and the palette state it drives
+pub struct Sgb {
+    packet: [u8; PACKET_SIZE],
+    bits: usize,
+    receiving: bool,
+    /// Set after a bit is read until P14 and P15 both go high again
+    bit_read: bool,
+    /// Last value written to P1, to catch P15 going high
+    last_write: u8,
+    players: usize,
+    player: usize,
+    command: Vec<u8>,
+    palettes: [[u16; 4]; 4],
+    system_palettes: Vec<[u16; 4]>,
+    attributes: [u8; CELLS],
+    attr_files: Vec<u8>,
+    mask: Mask,
+    transfer: Option<Transfer>,
+    transfer_delay: u8,
+    frame: Vec<u16>,
+    border_tiles: Vec<u8>,
+    border_map: Vec<u8>,
+    border_palettes: [[u16; 16]; BORDER_PALETTES],
+}
+
+impl Sgb {
+    pub(super) fn new() -> Self {
+        Self {
+            packet: [0; PACKET_SIZE],
+            bits: 0,
+            receiving: false,
+            bit_read: false,
+            last_write: 0xFF,
+            players: 1,
+            player: 0,
+            command: Vec::with_capacity(PACKET_SIZE * MAX_PACKETS),
+            palettes: [DEFAULT_PALETTE; 4],
+            system_palettes: vec![[0; 4]; SYSTEM_PALETTES],
+            attributes: [0; CELLS],
+            attr_files: vec![0; ATTR_FILES * ATTR_FILE_SIZE],
+            mask: Mask::None,
+            transfer: None,
+            transfer_delay: 0,
+            frame: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            border_tiles: vec![0; BORDER_TILES * BORDER_TILE_SIZE],
+            border_map: vec![0; BORDER_MAP_SIZE],
+            border_palettes: [[0; 16]; BORDER_PALETTES],
+        }
+    }
+
+    /// Decodes P14/P15 pulses: both low resets, P14 low sends a 0, P15 low
+    /// sends a 1 and both high ends the bit
+    pub(super) fn write_joypad(&mut self, data: u8) {
+        if self.players > 1 && self.last_write & 0x20 == 0 && data & 0x20 != 0 {
+            self.player = (self.player + 1) % self.players;
+        }
+        self.last_write = data;
+
+        match data & 0x30 {
+            0x00 => {
+                self.receiving = true;
+                self.bits = 0;
+                self.packet = [0; PACKET_SIZE];
+                self.bit_read = true;
+            }
+            0x30 => self.bit_read = false,
+            lines if self.receiving && !self.bit_read => {
+                self.bit_read = true;
+                if lines == 0x10 {
+                    self.packet[self.bits / 8] |= 1 << (self.bits % 8);
+                }
+                self.bits += 1;
+                if self.bits == PACKET_BITS {
+                    // The stop bit that follows is ignored
+                    self.receiving = false;
+                    self.receive_packet();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn receive_packet(&mut self) {
+        self.command.extend_from_slice(&self.packet);
+        let packets = (self.command[0] & 0x07).max(1) as usize;
+        if self.command.len() >= packets * PACKET_SIZE {
+            let command = std::mem::take(&mut self.command);
+            self.execute(&command);
+        }
+    }
+
+    fn execute(&mut self, data: &[u8]) {
+        match data[0] >> 3 {
+            PAL01 => self.set_palette_pair(0, 1, data),
+            PAL23 => self.set_palette_pair(2, 3, data),
+            PAL03 => self.set_palette_pair(0, 3, data),
+            PAL12 => self.set_palette_pair(1, 2, data),
+            ATTR_BLK => self.attr_blk(data),
+            ATTR_LIN => self.attr_lin(data),
+            ATTR_DIV => self.attr_div(data),
+            ATTR_CHR => self.attr_chr(data),
+            PAL_SET => self.pal_set(data),
+            MLT_REQ => {
+                self.players = match data[1] & 0x03 {
+                    1 => 2,
+                    3 => 4,
+                    _ => 1,
+                };
+                self.player = 0;
+            }
+            PAL_TRN => self.request_transfer(Transfer::Palettes),
+            ATTR_TRN => self.request_transfer(Transfer::Attributes),
+            CHR_TRN => {
+                let high = data[1] & 0x01 != 0;
+                self.request_transfer(Transfer::BorderTiles { high });
+            }
+            PCT_TRN => self.request_transfer(Transfer::Border),
+            ATTR_SET => {
+                self.apply_attr_file(data[1] & 0x3F);
+                if data[1] & 0x40 != 0 {
+                    self.mask = Mask::None;
+                }
+            }
+            MASK_EN => {
+                self.mask = match data[1] & 0x03 {
+                    0 => Mask::None,
+                    1 => Mask::Freeze,
+                    2 => Mask::Black,
+                    _ => Mask::Color0,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Color 0 is shared by all palettes
+    fn set_palette_pair(&mut self, a: usize, b: usize, data: &[u8]) {
+        let color = |i: usize| u16::from_le_bytes([data[1 + i * 2], data[2 + i * 2]]) & 0x7FFF;
+
+        for palette in self.palettes.iter_mut() {
+            palette[0] = color(0);
+        }
+        for i in 1..4 {
+            self.palettes[a][i] = color(i);
+            self.palettes[b][i] = color(i + 3);
+        }
+    }
+
+    fn set_cells(&mut self, x1: usize, y1: usize, x2: usize, y2: usize, palette: u8) {
+        for y in y1..=y2.min(CELLS_Y - 1) {
+            for x in x1..=x2.min(CELLS_X - 1) {
+                self.attributes[y * CELLS_X + x] = palette & 0x03;
+            }
+        }
+    }
+
+    fn attr_blk(&mut self, data: &[u8]) {
+        let count = data[1] as usize & 0x1F;
+        for set in data[2..].chunks_exact(6).take(count) {
+            let control = set[0] & 0x07;
+            let inside_palette = set[1] & 0x03;
+            let mut border_palette = (set[1] >> 2) & 0x03;
+            let outside_palette = (set[1] >> 4) & 0x03;
+            let (x1, y1) = (set[2] as usize, set[3] as usize);
+            let (x2, y2) = (set[4] as usize, set[5] as usize);
+
+            // With only the inside or outside set, the border takes its palette
+            let border = match control {
+                0b001 => {
+                    border_palette = inside_palette;
+                    true
+                }
+                0b100 => {
+                    border_palette = outside_palette;
+                    true
+                }
+                _ => control & 0b010 != 0,
+            };
+
+            for y in 0..CELLS_Y {
+                for x in 0..CELLS_X {
+                    let within = (x1..=x2).contains(&x) && (y1..=y2).contains(&y);
+                    let on_border = within && (x == x1 || x == x2 || y == y1 || y == y2);
+
+                    let palette = match (within, on_border) {
+                        (true, false) if control & 0b001 != 0 => inside_palette,
+                        (true, true) if border => border_palette,
+                        (false, _) if control & 0b100 != 0 => outside_palette,
+                        _ => continue,
+                    };
+                    self.attributes[y * CELLS_X + x] = palette;
+                }
+            }
+        }
+    }
+
+    fn attr_lin(&mut self, data: &[u8]) {
+        let count = data[1] as usize;
+        for &line in data[2..].iter().take(count) {
+            let index = (line & 0x1F) as usize;
+            let palette = (line >> 5) & 0x03;
+            match line & 0x80 != 0 {
+                true => self.set_cells(0, index, CELLS_X - 1, index, palette),
+                false => self.set_cells(index, 0, index, CELLS_Y - 1, palette),
+            }
+        }
+    }
+
+    fn attr_div(&mut self, data: &[u8]) {
+        let after = data[1] & 0x03;
+        let before = (data[1] >> 2) & 0x03;
+        let on = (data[1] >> 4) & 0x03;
+        let horizontal = data[1] & 0x40 != 0;
+        let split = data[2] as usize;
+
+        for y in 0..CELLS_Y {
+            for x in 0..CELLS_X {
+                let position = match horizontal {
+                    true => y,
+                    false => x,
+                };
+                let palette = match position.cmp(&split) {
+                    std::cmp::Ordering::Less => before,
+                    std::cmp::Ordering::Equal => on,
+                    std::cmp::Ordering::Greater => after,
+                };
+                self.attributes[y * CELLS_X + x] = palette;
+            }
+        }
+    }
+
+    fn attr_chr(&mut self, data: &[u8]) {
+        let (mut x, mut y) = (data[1] as usize, data[2] as usize);
+        let count = (u16::from_le_bytes([data[3], data[4]]) as usize).min(CELLS);
+        let vertical = data[5] & 0x01 != 0;
+
+        for i in 0..count {
+            let Some(&byte) = data.get(6 + i / 4) else {
+                break;
+            };
+            if x >= CELLS_X || y >= CELLS_Y {
+                break;
+            }
+            self.attributes[y * CELLS_X + x] = (byte >> (6 - (i % 4) * 2)) & 0x03;
+
+            match vertical {
+                true => {
+                    y += 1;
+                    if y == CELLS_Y {
+                        y = 0;
+                        x += 1;
+                    }
+                }
+                false => {
+                    x += 1;
+                    if x == CELLS_X {
+                        x = 0;
+                        y += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    fn pal_set(&mut self, data: &[u8]) {
+        for i in 0..4 {
+            let index = u16::from_le_bytes([data[1 + i * 2], data[2 + i * 2]]) as usize & 0x1FF;
+            self.palettes[i] = self.system_palettes[index];
+        }
+
+        // Color 0 of palette 0 is shared by all palettes
+        let color0 = self.palettes[0][0];
+        for palette in self.palettes.iter_mut() {
+            palette[0] = color0;
+        }
+
+        let flags = data[9];
+        if flags & 0x80 != 0 {
+            self.apply_attr_file(flags & 0x3F);
+        }
+        if flags & 0x40 != 0 {
+            self.mask = Mask::None;
+        }
+    }
+
+    fn apply_attr_file(&mut self, file: u8) {
+        let file = file as usize;
+        if file >= ATTR_FILES {
+            return;
+        }
+
+        let data = &self.attr_files[file * ATTR_FILE_SIZE..(file + 1) * ATTR_FILE_SIZE];
+        for (i, cell) in self.attributes.iter_mut().enumerate() {
+            *cell = (data[i / 4] >> (6 - (i % 4) * 2)) & 0x03;
+        }
+    }
+
+    fn request_transfer(&mut self, transfer: Transfer) {
+        self.transfer = Some(transfer);
+        self.transfer_delay = TRANSFER_DELAY;
+    }
+
+    /// Receives a frame of DMG shades from the PPU, reading a pending VRAM
+    /// transfer from it and coloring it
+    pub(super) fn end_frame(&mut self, shades: &[u8]) {
+        if self.transfer.is_some() {
+            self.transfer_delay = self.transfer_delay.saturating_sub(1);
+            if self.transfer_delay == 0 {
+                if let Some(transfer) = self.transfer.take() {
+                    self.transfer(transfer, &Self::screen_tiles(shades));
+                }
+            }
+        }
+        self.colorize(shades);
+    }
+
+    /// Encodes the screen back into `TRANSFER_SIZE` bytes of 2bpp tiles, read
+    /// 20 tiles per row from the top left like the SGB does
+    fn screen_tiles(shades: &[u8]) -> Vec<u8> {
+        let mut data = vec![0; TRANSFER_SIZE];
+        for (tile, bytes) in data.chunks_exact_mut(16).enumerate() {
+            let (tx, ty) = (tile % CELLS_X, tile / CELLS_X);
+            for (row, planes) in bytes.chunks_exact_mut(2).enumerate() {
+                let start = (ty * 8 + row) * SCREEN_WIDTH + tx * 8;
+                for (col, &shade) in shades[start..start + 8].iter().enumerate() {
+                    planes[0] |= (shade & 0x01) << (7 - col);
+                    planes[1] |= ((shade >> 1) & 0x01) << (7 - col);
+                }
+            }
+        }
+        data
+    }
+
+    /// Receives `TRANSFER_SIZE` bytes of tile data for a VRAM transfer
+    fn transfer(&mut self, transfer: Transfer, data: &[u8]) {
+        match transfer {
+            Transfer::Palettes => {
+                for (palette, colors) in self.system_palettes.iter_mut().zip(data.chunks_exact(8)) {
+                    for (i, color) in colors.chunks_exact(2).enumerate() {
+                        palette[i] = u16::from_le_bytes([color[0], color[1]]) & 0x7FFF;
+                    }
+                }
+            }
+            Transfer::Attributes => {
+                let len = self.attr_files.len().min(data.len());
+                self.attr_files[..len].copy_from_slice(&data[..len]);
+            }
+            Transfer::BorderTiles { high } => {
+                let start = high as usize * TRANSFER_SIZE;
+                self.border_tiles[start..start + TRANSFER_SIZE]
+                    .copy_from_slice(&data[..TRANSFER_SIZE]);
+            }
+            Transfer::Border => {
+                self.border_map.copy_from_slice(&data[..BORDER_MAP_SIZE]);
+                let colors = data[BORDER_MAP_SIZE..].chunks_exact(2);
+                for (i, color) in colors.take(BORDER_PALETTES * 16).enumerate() {
+                    self.border_palettes[i / 16][i % 16] =
+                        u16::from_le_bytes([color[0], color[1]]) & 0x7FFF;
+                }
+            }
+        }
+    }
+
+    /// Controllers enabled by MLT_REQ
+    pub fn players(&self) -> usize {
+        self.players
+    }
+
+    /// Controller currently read through P1
+    pub fn player(&self) -> usize {
+        self.player
+    }
+
+    pub fn palettes(&self) -> &[[u16; 4]; 4] {
+        &self.palettes
+    }
+
+    /// Palette number of each 8x8 cell of the screen, row by row
+    pub fn attributes(&self) -> &[u8] {
+        &self.attributes
+    }
+
+    pub fn mask(&self) -> Mask {
+        self.mask
+    }
+
+    /// Draws the 256x224 border in BGR555 around `game`, a colorized frame.
+    /// Transparent border pixels show the game or color 0 of palette 0.
+    pub fn render_border(&self, game: &[u16]) -> Vec<u16> {
+        let mut out = vec![self.palettes[0][0]; BORDER_WIDTH * BORDER_HEIGHT];
+
+        for (y, line) in game
+            .chunks_exact(SCREEN_WIDTH)
+            .take(SCREEN_HEIGHT)
+            .enumerate()
+        {
+            let start = (y + GAME_Y) * BORDER_WIDTH + GAME_X;
+            out[start..start + SCREEN_WIDTH].copy_from_slice(line);
+        }
+
+        for (i, entry) in self.border_map.chunks_exact(2).enumerate() {
+            let (tx, ty) = (i % BORDER_MAP_WIDTH, i / BORDER_MAP_WIDTH);
+            if ty * 8 >= BORDER_HEIGHT {
+                break;
+            }
+
+            let entry = u16::from_le_bytes([entry[0], entry[1]]);
+            let tile = &self.border_tiles[(entry & 0xFF) as usize * BORDER_TILE_SIZE..]
+                [..BORDER_TILE_SIZE];
+            // Border palettes are numbered 4-7
+            let palette = &self.border_palettes[((entry >> 10) & 0x03) as usize];
+            let x_flip = entry & 0x4000 != 0;
+            let y_flip = entry & 0x8000 != 0;
+
+            for row in 0..8 {
+                let r = match y_flip {
+                    true => 7 - row,
+                    false => row,
+                };
+                let planes = [
+                    tile[r * 2],
+                    tile[r * 2 + 1],
+                    tile[16 + r * 2],
+                    tile[16 + r * 2 + 1],
+                ];
+
+                for col in 0..8 {
+                    let bit = match x_flip {
+                        true => col,
+                        false => 7 - col,
+                    };
+                    let color = planes
+                        .iter()
+                        .enumerate()
+                        .fold(0, |acc, (p, plane)| acc | ((plane >> bit) & 0x01) << p);
+                    if color != 0 {
+                        out[(ty * 8 + row) * BORDER_WIDTH + tx * 8 + col] = palette[color as usize];
+                    }
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Colors a 160x144 frame of DMG shades (0-3, after BGP/OBP) into BGR555
+    fn colorize(&mut self, shades: &[u8]) {
+        match self.mask {
+            Mask::Freeze => {}
+            Mask::Black => self.frame.fill(0),
+            Mask::Color0 => self.frame.fill(self.palettes[0][0]),
+            Mask::None => {
+                for (i, (pixel, &shade)) in self.frame.iter_mut().zip(shades).enumerate() {
+                    let cell = (i / SCREEN_WIDTH / 8) * CELLS_X + (i % SCREEN_WIDTH) / 8;
+                    let palette = self.attributes[cell] as usize;
+                    *pixel = self.palettes[palette][shade as usize & 0x03];
+                }
+            }
+        }
+    }
+
+    /// Last frame colored with the SGB palettes and attributes
+    pub fn frame(&self) -> &[u16] {
+        &self.frame
+    }
+}
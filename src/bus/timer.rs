@@ -1,3 +1,5 @@
+use crate::model::Model;
+
 use super::interrupts::{InterruptFlag, Interrupts};
 
 const DIV: u16 = 0xFF04;
@@ -13,9 +15,27 @@ pub struct Timer {
 }
 
 impl Timer {
-    pub const fn new() -> Self {
+    /// Internal divider as left by the boot ROM of `model`
+    pub const fn new(model: Model) -> Self {
+        let div = match model {
+            Model::Dmg0 => 0x182C,
+            Model::Dmg | Model::Mgb => 0xABCC,
+            Model::Sgb | Model::Sgb2 => 0xD85C,
+            Model::Cgb | Model::Agb => 0x267C,
+        };
+
+        Self {
+            div,
+            tima: 0,
+            tma: 0,
+            tac: 0,
+        }
+    }
+
+    /// State before the boot ROM runs
+    pub const fn power_on() -> Self {
         Self {
-            div: 0xABCC,
+            div: 0,
             tima: 0,
             tma: 0,
             tac: 0,
@@ -0,0 +1,283 @@
+use super::compat::CompatPalette;
+
+const VRAM_BANK_SIZE: usize = 0x2000;
+const VRAM_MASK: usize = VRAM_BANK_SIZE - 1;
+
+const OAM_SIZE: usize = 0xA0;
+const OBJECT_SIZE: usize = 4;
+const OBJECTS_PER_LINE: usize = 10;
+
+const PALETTE_RAM_SIZE: usize = 0x40;
+
+const VBK: u16 = 0xFF4F;
+const BCPS: u16 = 0xFF68;
+const BCPD: u16 = 0xFF69;
+const OCPS: u16 = 0xFF6A;
+const OCPD: u16 = 0xFF6B;
+const OPRI: u16 = 0xFF6C;
+
+/// Attributes of a background tile, stored in VRAM bank 1 at the same address
+/// as the tile number. Objects use the same layout for their OAM flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileAttributes {
+    /// CGB palette number
+    pub palette: u8,
+    /// OBP0/OBP1 selection for objects in DMG mode
+    pub dmg_palette: u8,
+    pub bank: u8,
+    pub x_flip: bool,
+    pub y_flip: bool,
+    /// Background over objects, or object behind background
+    pub priority: bool,
+}
+
+impl TileAttributes {
+    pub const fn from_bits(bits: u8) -> Self {
+        Self {
+            palette: bits & 0x07,
+            dmg_palette: (bits >> 4) & 0x01,
+            bank: (bits >> 3) & 0x01,
+            x_flip: bits & 0x20 != 0,
+            y_flip: bits & 0x40 != 0,
+            priority: bits & 0x80 != 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Object {
+    pub index: usize,
+    pub y: u8,
+    pub x: u8,
+    pub tile: u8,
+    pub attributes: TileAttributes,
+}
+
+/// 8 palettes of 4 colors in little-endian BGR555, accessed through an index
+/// register with optional auto-increment
+pub struct ColorPalettes {
+    data: [u8; PALETTE_RAM_SIZE],
+    index: u8,
+    auto_increment: bool,
+}
+
+impl ColorPalettes {
+    const fn new() -> Self {
+        Self {
+            data: [0xFF; PALETTE_RAM_SIZE],
+            index: 0,
+            auto_increment: false,
+        }
+    }
+
+    const fn spec(&self) -> u8 {
+        (self.auto_increment as u8) << 7 | 0x40 | self.index
+    }
+
+    fn set_spec(&mut self, data: u8) {
+        self.index = data & 0x3F;
+        self.auto_increment = data & 0x80 != 0;
+    }
+
+    const fn data(&self) -> u8 {
+        self.data[self.index as usize]
+    }
+
+    fn set_data(&mut self, data: u8) {
+        self.data[self.index as usize] = data;
+        if self.auto_increment {
+            self.index = (self.index + 1) & 0x3F;
+        }
+    }
+
+    fn set_palette(&mut self, palette: usize, colors: [u16; 4]) {
+        for (i, color) in colors.into_iter().enumerate() {
+            let offset = (palette * 4 + i) * 2;
+            self.data[offset..offset + 2].copy_from_slice(&color.to_le_bytes());
+        }
+    }
+
+    /// 15-bit color `color` of palette `palette`
+    pub const fn color(&self, palette: u8, color: u8) -> u16 {
+        let i = ((palette as usize & 0x07) * 4 + (color as usize & 0x03)) * 2;
+        u16::from_le_bytes([self.data[i], self.data[i + 1]]) & 0x7FFF
+    }
+}
+
+/// VRAM, OAM and the CGB palette and banking registers the PPU draws from
+pub struct Video {
+    vram: [u8; VRAM_BANK_SIZE * 2],
+    vram_bank: usize,
+    oam: [u8; OAM_SIZE],
+    bg_palettes: ColorPalettes,
+    obj_palettes: ColorPalettes,
+    /// OPRI bit 0, set when objects are prioritized by X like on DMG
+    dmg_priority: bool,
+    cgb_mode: bool,
+}
+
+impl Video {
+    pub const fn new(cgb_mode: bool) -> Self {
+        Self {
+            vram: [0; VRAM_BANK_SIZE * 2],
+            vram_bank: 0,
+            oam: [0; OAM_SIZE],
+            bg_palettes: ColorPalettes::new(),
+            obj_palettes: ColorPalettes::new(),
+            dmg_priority: !cgb_mode,
+            cgb_mode,
+        }
+    }
+
+    /// Whether CGB features are enabled, false for DMG models and DMG games
+    /// running on CGB
+    pub const fn cgb_mode(&self) -> bool {
+        self.cgb_mode
+    }
+
+    pub fn set_cgb_mode(&mut self, cgb_mode: bool) {
+        self.cgb_mode = cgb_mode;
+        self.dmg_priority = !cgb_mode;
+        if !cgb_mode {
+            self.vram_bank = 0;
+        }
+    }
+
+    /// Loads the colors DMG games are shown with on CGB into BG palette 0
+    /// and OBJ palettes 0 and 1
+    pub fn set_compat_palette(&mut self, palette: &CompatPalette) {
+        self.bg_palettes.set_palette(0, palette.bg);
+        self.obj_palettes.set_palette(0, palette.obj0);
+        self.obj_palettes.set_palette(1, palette.obj1);
+    }
+
+    pub const fn vram_read(&self, address: u16) -> u8 {
+        self.vram[self.vram_bank * VRAM_BANK_SIZE + (address as usize & VRAM_MASK)]
+    }
+
+    pub fn vram_write(&mut self, address: u16, data: u8) {
+        self.vram[self.vram_bank * VRAM_BANK_SIZE + (address as usize & VRAM_MASK)] = data;
+    }
+
+    pub const fn oam_read(&self, address: u16) -> u8 {
+        self.oam[address as usize & 0xFF]
+    }
+
+    pub fn oam_write(&mut self, address: u16, data: u8) {
+        self.oam[address as usize & 0xFF] = data;
+    }
+
+    /// Reads a CGB register, which all read $FF in DMG mode
+    pub const fn read(&self, address: u16) -> u8 {
+        if !self.cgb_mode {
+            return 0xFF;
+        }
+
+        match address {
+            VBK => 0xFE | self.vram_bank as u8,
+            BCPS => self.bg_palettes.spec(),
+            BCPD => self.bg_palettes.data(),
+            OCPS => self.obj_palettes.spec(),
+            OCPD => self.obj_palettes.data(),
+            OPRI => 0xFE | self.dmg_priority as u8,
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write(&mut self, address: u16, data: u8) {
+        if !self.cgb_mode {
+            return;
+        }
+
+        match address {
+            VBK => self.vram_bank = (data & 0x01) as usize,
+            BCPS => self.bg_palettes.set_spec(data),
+            BCPD => self.bg_palettes.set_data(data),
+            OCPS => self.obj_palettes.set_spec(data),
+            OCPD => self.obj_palettes.set_data(data),
+            OPRI => self.dmg_priority = data & 0x01 != 0,
+            _ => {}
+        }
+    }
+
+    /// One of the two 8 KiB VRAM banks, bank 1 only exists in CGB mode
+    pub fn vram(&self, bank: usize) -> &[u8] {
+        let bank = bank & 0x01;
+        &self.vram[bank * VRAM_BANK_SIZE..(bank + 1) * VRAM_BANK_SIZE]
+    }
+
+    /// Attributes of the background map entry at `address` ($9800-$9FFF)
+    pub const fn tile_attributes(&self, address: u16) -> TileAttributes {
+        match self.cgb_mode {
+            true => TileAttributes::from_bits(
+                self.vram[VRAM_BANK_SIZE + (address as usize & VRAM_MASK)],
+            ),
+            false => TileAttributes::from_bits(0),
+        }
+    }
+
+    pub const fn bg_palettes(&self) -> &ColorPalettes {
+        &self.bg_palettes
+    }
+
+    pub const fn obj_palettes(&self) -> &ColorPalettes {
+        &self.obj_palettes
+    }
+
+    pub fn objects(&self) -> impl Iterator<Item = Object> + '_ {
+        self.oam
+            .chunks_exact(OBJECT_SIZE)
+            .enumerate()
+            .map(|(index, obj)| Object {
+                index,
+                y: obj[0],
+                x: obj[1],
+                tile: obj[2],
+                attributes: TileAttributes::from_bits(obj[3]),
+            })
+    }
+
+    /// Objects drawn on line `ly`, highest priority first. The first 10 in OAM
+    /// order are selected, then ordered by OAM index in CGB mode, or by X
+    /// coordinate and OAM index with DMG priority.
+    pub fn line_objects(&self, ly: u8, tall: bool) -> Vec<Object> {
+        let height = match tall {
+            true => 16,
+            false => 8,
+        };
+        let line = ly as u16 + 16;
+
+        let mut objects: Vec<Object> = self
+            .objects()
+            .filter(|obj| (obj.y as u16..obj.y as u16 + height).contains(&line))
+            .take(OBJECTS_PER_LINE)
+            .collect();
+
+        if self.dmg_priority {
+            objects.sort_by_key(|obj| (obj.x, obj.index));
+        }
+        objects
+    }
+
+    /// Whether an opaque pixel of `object` is drawn over a background pixel of
+    /// color index `bg_color`. In CGB mode, clearing LCDC bit 0 (`bg_enabled`)
+    /// puts objects over everything and a background attribute priority bit
+    /// puts the background over objects.
+    pub const fn object_visible(
+        &self,
+        object: &Object,
+        bg: TileAttributes,
+        bg_color: u8,
+        bg_enabled: bool,
+    ) -> bool {
+        if bg_color == 0 {
+            return true;
+        }
+
+        match self.cgb_mode {
+            true if !bg_enabled => true,
+            true if bg.priority => false,
+            _ => !object.attributes.priority,
+        }
+    }
+}
@@ -0,0 +1,82 @@
+/// Conversion of 15-bit CGB colors to 24-bit RGB
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorCorrection {
+    /// Channels scaled as-is, oversaturated compared to real screens
+    #[default]
+    Raw,
+    /// Game Boy Color LCD
+    Gbc,
+    /// Game Boy Advance LCD, darker than the GBC one
+    Gba,
+}
+
+/// Response of an LCD: gamma of the panel, overall brightness and how much of
+/// each input channel (columns R, G, B) ends up in each output channel
+struct Screen {
+    gamma: f32,
+    luminance: f32,
+    mix: [[f32; 3]; 3],
+}
+
+/// Gamma of the monitor the corrected colors are shown on
+const DISPLAY_GAMMA: f32 = 2.2;
+
+// Values from the libretro handheld color shaders
+const GBC_SCREEN: Screen = Screen {
+    gamma: 2.2,
+    luminance: 0.94,
+    mix: [
+        [0.82, 0.24, -0.06],
+        [0.125, 0.665, 0.21],
+        [0.195, 0.075, 0.73],
+    ],
+};
+
+const GBA_SCREEN: Screen = Screen {
+    gamma: 2.7,
+    luminance: 0.93,
+    mix: [
+        [0.845, 0.17, -0.015],
+        [0.09, 0.68, 0.23],
+        [0.16, 0.085, 0.755],
+    ],
+};
+
+impl ColorCorrection {
+    pub const ALL: [ColorCorrection; 3] = [
+        ColorCorrection::Raw,
+        ColorCorrection::Gbc,
+        ColorCorrection::Gba,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            ColorCorrection::Raw => "raw",
+            ColorCorrection::Gbc => "gbc",
+            ColorCorrection::Gba => "gba",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Converts a little-endian BGR555 color as stored in palette RAM
+    pub fn rgb(self, color: u16) -> [u8; 3] {
+        let channels = [color & 0x1F, (color >> 5) & 0x1F, (color >> 10) & 0x1F];
+        let screen = match self {
+            ColorCorrection::Raw => return channels.map(|c| ((c << 3) | (c >> 2)) as u8),
+            ColorCorrection::Gbc => &GBC_SCREEN,
+            ColorCorrection::Gba => &GBA_SCREEN,
+        };
+
+        let linear = channels.map(|c| (c as f32 / 31.0).powf(screen.gamma) * screen.luminance);
+        screen.mix.map(|row| {
+            let mixed = row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2];
+            let encoded = mixed.clamp(0.0, 1.0).powf(1.0 / DISPLAY_GAMMA);
+            (encoded * 255.0).round() as u8
+        })
+    }
+}
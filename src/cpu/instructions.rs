@@ -32,7 +32,7 @@ impl Cpu {
     pub fn execute(&mut self, bus: &mut impl Interface) {
         match self.cur_opcode {
             0x00 => self.nop(),
-            0x10 => self.stop(),
+            0x10 => self.stop(bus),
             0x27 => self.daa(),
             0x2F => self.cpl(),
             0x37 => self.scf(),
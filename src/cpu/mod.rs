@@ -1,4 +1,7 @@
-use crate::bus::interrupts::InterruptFlag;
+use crate::{
+    bus::{cartridge::header::Header, interrupts::InterruptFlag},
+    model::Model,
+};
 
 use self::{
     dbg::Dbg,
@@ -16,6 +19,8 @@ pub struct Cpu {
     cur_opcode: u8,
     regs: Registers,
     halted: bool,
+    /// Low-power mode entered by STOP, left on joypad input
+    stopped: bool,
     ime: bool,
     enabling_ime: bool,
     dbg: Dbg,
@@ -34,17 +39,30 @@ pub trait Interface {
         self.tick(1);
     }
     fn tick(&mut self, count: usize);
+    /// Handles STOP, returns true when the CPU should sleep until joypad input
+    fn stop(&mut self) -> bool;
+    fn joypad_input(&self) -> bool;
     fn cycles(&self) -> u64;
     fn check_interrupts(&self) -> InterruptFlag;
     fn interrupt_handled(&mut self, intr: InterruptFlag);
 }
 
 impl Cpu {
-    pub const fn new() -> Self {
+    pub const fn new(model: Model, header: &Header) -> Self {
+        Self::with_registers(Registers::new(model, header))
+    }
+
+    /// CPU at power-on, for starting from a boot ROM
+    pub const fn power_on() -> Self {
+        Self::with_registers(Registers::power_on())
+    }
+
+    const fn with_registers(regs: Registers) -> Self {
         Self {
             cur_opcode: 0x00,
-            regs: Registers::new(),
+            regs,
             halted: false,
+            stopped: false,
             ime: false,
             enabling_ime: false,
             dbg: Dbg::new(),
@@ -62,6 +80,13 @@ impl Cpu {
     }
 
     pub fn step(&mut self, bus: &mut impl Interface) {
+        if self.stopped {
+            match bus.joypad_input() {
+                true => self.stopped = false,
+                false => return,
+            }
+        }
+
         // Last instruction was DI
         if self.cur_opcode == 0xF3 {
             self.fetch_instruction(bus);
@@ -420,8 +445,10 @@ impl Cpu {
 
     fn nop(&self) {}
 
-    fn stop(&self) {
-        panic!("Stop instruction");
+    fn stop(&mut self, bus: &mut impl Interface) {
+        // STOP is encoded as $10 $00, the second byte is skipped
+        self.imm(bus);
+        self.stopped = bus.stop();
     }
 
     fn halt(&mut self) {
@@ -1,5 +1,10 @@
 use bitflags::bitflags;
 
+use crate::{
+    bus::cartridge::header::{CgbSupport, Header},
+    model::Model,
+};
+
 bitflags! {
     pub struct Flags: u8 {
         const Z = 0b1000_0000;
@@ -43,21 +48,80 @@ pub struct Registers {
 }
 
 impl Registers {
-    pub const fn new() -> Self {
+    /// State the boot ROM of `model` leaves behind when it hands over to the cartridge
+    pub const fn new(model: Model, header: &Header) -> Self {
+        let dmg_flags = match header.stored_checksum {
+            0x00 => Flags::Z,
+            _ => Flags::from_bits_truncate(Flags::Z.bits | Flags::H.bits | Flags::C.bits),
+        };
+
+        let (a, f, b, c, d, e, h, l) = match model {
+            Model::Dmg0 => (0x01, Flags::empty(), 0xFF, 0x13, 0x00, 0xC1, 0x84, 0x03),
+            Model::Dmg => (0x01, dmg_flags, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D),
+            Model::Mgb => (0xFF, dmg_flags, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D),
+            Model::Sgb => (0x01, Flags::empty(), 0x00, 0x14, 0x00, 0x00, 0xC0, 0x60),
+            Model::Sgb2 => (0xFF, Flags::empty(), 0x00, 0x14, 0x00, 0x00, 0xC0, 0x60),
+            Model::Cgb | Model::Agb => match header.cgb {
+                CgbSupport::Enhanced | CgbSupport::Only => {
+                    (0x11, Flags::Z, 0x00, 0x00, 0xFF, 0x56, 0x00, 0x0D)
+                }
+                CgbSupport::None => {
+                    let b = header.title_hash;
+                    let (h, l) = match b {
+                        0x43 | 0x58 => (0x99, 0x1A),
+                        _ => (0x00, 0x7C),
+                    };
+                    (0x11, Flags::Z, b, 0x00, 0x00, 0x08, h, l)
+                }
+            },
+        };
+
+        // The AGB boot ROM ends with an extra INC B
+        let (f, b) = match model {
+            Model::Agb => {
+                let b = b.wrapping_add(1);
+                let mut f = 0;
+                if b == 0 {
+                    f |= Flags::Z.bits;
+                }
+                if b & 0x0F == 0 {
+                    f |= Flags::H.bits;
+                }
+                (Flags::from_bits_truncate(f), b)
+            }
+            _ => (f, b),
+        };
+
         Self {
-            a: 0x01,
-            f: Flags::from_bits_truncate(0xB0),
-            b: 0x00,
-            c: 0x13,
-            d: 0x00,
-            e: 0xD8,
-            h: 0x01,
-            l: 0x4D,
+            a,
+            f,
+            b,
+            c,
+            d,
+            e,
+            h,
+            l,
             pc: 0x0100,
             sp: 0xFFFE,
         }
     }
 
+    /// State before the boot ROM runs
+    pub const fn power_on() -> Self {
+        Self {
+            a: 0x00,
+            f: Flags::empty(),
+            b: 0x00,
+            c: 0x00,
+            d: 0x00,
+            e: 0x00,
+            h: 0x00,
+            l: 0x00,
+            pc: 0x0000,
+            sp: 0x0000,
+        }
+    }
+
     pub const fn zf(&self) -> bool {
         self.f.contains(Flags::Z)
     }
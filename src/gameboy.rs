@@ -1,31 +1,244 @@
-use sdl2::{event::Event, keyboard::Keycode, EventPump};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use sdl2::{
+    controller::{Button, GameController},
+    event::Event,
+    keyboard::Keycode,
+    pixels::PixelFormatEnum,
+    render::{Canvas, Texture},
+    video::Window,
+    EventPump, GameControllerSubsystem,
+};
 
 use crate::{
-    bus::{cartridge::Cartridge, joypad::GbButton, Bus},
+    bus::{
+        boot::BootRomError,
+        cartridge::{
+            header::{Header, HeaderError},
+            Cartridge,
+        },
+        cheats::{Cheat, CheatError},
+        compat::CompatPalette,
+        joypad::{GbButton, MAX_PLAYERS},
+        ppu::{SCREEN_HEIGHT, SCREEN_WIDTH},
+        search::{Candidate, CheatSearch, SearchFilter},
+        sgb::{Sgb, BORDER_HEIGHT, BORDER_WIDTH},
+        video::Video,
+        Bus,
+    },
+    color::ColorCorrection,
     cpu::Cpu,
+    model::Model,
 };
 
+const WINDOW_WIDTH: u32 = 800;
+const WINDOW_HEIGHT: u32 = 600;
+
+/// Length of a frame, 70224 dots at 4.194304 MHz
+const FRAME_DURATION: Duration = Duration::from_nanos(16_742_706);
+
+/// Delay after the last external RAM write before the save file is flushed
+const SAVE_DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// Rumble is surfaced about once per frame, games drive the motor with PWM
+const RUMBLE_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Controller rumble is renewed every interval, this covers a late one
+const RUMBLE_DURATION_MS: u32 = 50;
+
 pub struct Gameboy {
     cpu: Cpu,
     bus: Bus,
+    model: Model,
+    color_correction: ColorCorrection,
+    show_border: bool,
+    save_path: Option<PathBuf>,
+    last_write: Option<Instant>,
+    search: Option<CheatSearch>,
 }
 
 impl Gameboy {
-    pub fn new(rom: Vec<u8>) -> Self {
-        let cartridge = Cartridge::new(rom);
+    /// Starts from the state the boot ROM of `model` leaves behind, the model
+    /// is detected from the header when `None`
+    pub fn new(rom: Vec<u8>, model: Option<Model>) -> Result<Self, HeaderError> {
+        let cartridge = Cartridge::new(rom)?;
         cartridge.print_header();
 
-        Self {
-            cpu: Cpu::new(),
-            bus: Bus::new(cartridge),
+        let model = model.unwrap_or_else(|| Model::detect(cartridge.header()));
+        println!("Running as {}", model);
+
+        Ok(Self {
+            cpu: Cpu::new(model, cartridge.header()),
+            bus: Bus::new(cartridge, model),
+            model,
+            color_correction: ColorCorrection::default(),
+            show_border: true,
+            save_path: None,
+            last_write: None,
+            search: None,
+        })
+    }
+
+    /// Starts execution at $0000 from `data` instead of the post-boot state
+    pub fn load_boot_rom(&mut self, data: Vec<u8>) -> Result<(), BootRomError> {
+        self.bus.load_boot_rom(data)?;
+        self.cpu = Cpu::power_on();
+        Ok(())
+    }
+
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    pub fn color_correction(&self) -> ColorCorrection {
+        self.color_correction
+    }
+
+    pub fn set_color_correction(&mut self, mode: ColorCorrection) {
+        self.color_correction = mode;
+    }
+
+    /// Overrides the title-based palette of a DMG game running on CGB, like
+    /// holding a button combination during the boot logo
+    pub fn set_compat_palette(&mut self, palette: &CompatPalette) -> bool {
+        self.bus.set_compat_palette(palette)
+    }
+
+    /// Super Game Boy state, when running an SGB game on an SGB model
+    pub fn sgb(&self) -> Option<&Sgb> {
+        self.bus.sgb()
+    }
+
+    /// Presses `button` on controller `player`, 0 to 3 with SGB multiplayer
+    pub fn keydown(&mut self, player: usize, button: GbButton) {
+        self.bus.keydown(player, button);
+    }
+
+    pub fn keyup(&mut self, player: usize, button: GbButton) {
+        self.bus.keyup(player, button);
+    }
+
+    /// Whether the frontend draws the SGB border
+    pub fn set_show_border(&mut self, show: bool) {
+        self.show_border = show;
+    }
+
+    /// VRAM, OAM and CGB palettes, for renderers and tests
+    pub fn video(&self) -> &Video {
+        self.bus.video()
+    }
+
+    /// Last frame, 160x144 colors in BGR555, colored by the SGB in SGB mode
+    pub fn screen(&self) -> &[u16] {
+        match self.sgb() {
+            Some(sgb) => sgb.frame(),
+            None => self.bus.ppu().frame(),
+        }
+    }
+
+    pub fn header(&self) -> &Header {
+        self.bus.cartridge().header()
+    }
+
+    /// Uses `path` as the save file for battery-backed or flash cartridges,
+    /// loading it if it exists
+    pub fn load_save(&mut self, path: impl Into<PathBuf>) -> io::Result<()> {
+        if !self.bus.cartridge().has_save() {
+            return Ok(());
+        }
+
+        let path = path.into();
+        match fs::read(&path) {
+            Ok(data) => self.bus.cartridge_mut().load_save_data(&data),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        self.save_path = Some(path);
+
+        Ok(())
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        match &self.save_path {
+            Some(path) => fs::write(path, self.bus.cartridge().save_data()),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the save file if battery-backed data changed since the last save
+    pub fn flush_save(&mut self) -> io::Result<()> {
+        let dirty = self.bus.cartridge_mut().take_dirty();
+        match self.last_write.take().is_some() || dirty {
+            true => self.save(),
+            false => Ok(()),
+        }
+    }
+
+    /// Loads cheats from a text file, see `Cheats::load` for the format.
+    /// Returns the invalid lines that were skipped.
+    pub fn load_cheats(&mut self, path: impl AsRef<Path>) -> io::Result<Vec<(usize, CheatError)>> {
+        let text = fs::read_to_string(path)?;
+        Ok(self.bus.load_cheats(&text))
+    }
+
+    pub fn add_cheat(&mut self, code: &str) -> Result<(), CheatError> {
+        self.bus.add_cheat(code)
+    }
+
+    pub fn cheats(&self) -> &[Cheat] {
+        self.bus.cheats()
+    }
+
+    pub fn toggle_cheat(&mut self, index: usize) -> Option<bool> {
+        self.bus.toggle_cheat(index)
+    }
+
+    /// Snapshots every WRAM and cartridge RAM bank and HRAM, starting a new cheat search
+    pub fn start_search(&mut self) {
+        self.search = Some(CheatSearch::new(&self.bus));
+    }
+
+    /// Narrows the current search down, returning the number of candidates left
+    pub fn filter_search(&mut self, filter: SearchFilter) -> usize {
+        match &mut self.search {
+            Some(search) => search.filter(&self.bus, filter),
+            None => 0,
         }
     }
 
+    pub fn search_candidates(&self) -> &[Candidate] {
+        match &self.search {
+            Some(search) => search.candidates(),
+            None => &[],
+        }
+    }
+
+    pub fn rumble(&self) -> bool {
+        self.bus.rumble()
+    }
+
+    /// Tilt of the cartridge accelerometer in g, positive is right/down
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        self.bus.set_tilt(x, y);
+    }
+
+    /// Runs until the window is closed, the save file is flushed however the
+    /// emulation stops
     pub fn run(&mut self) -> Result<(), String> {
+        let result = self.run_window();
+        let saved = self.flush_save().map_err(|e| e.to_string());
+        result.and(saved)
+    }
+
+    fn run_window(&mut self) -> Result<(), String> {
         let sdl_context = sdl2::init()?;
         let video = sdl_context.video()?;
         let window = video
-            .window("GbOxyde", 800, 600)
+            .window("GbOxyde", WINDOW_WIDTH, WINDOW_HEIGHT)
             .position_centered()
             .build()
             .map_err(|e| e.to_string())?;
@@ -35,25 +248,241 @@ impl Gameboy {
             .build()
             .map_err(|e| e.to_string())?;
 
+        let game_controller = sdl_context.game_controller()?;
+        let mut controllers = Default::default();
+
+        let texture_creator = canvas.texture_creator();
+        let mut border = texture_creator
+            .create_texture_streaming(
+                PixelFormatEnum::RGB24,
+                BORDER_WIDTH as u32,
+                BORDER_HEIGHT as u32,
+            )
+            .map_err(|e| e.to_string())?;
+        let mut screen = texture_creator
+            .create_texture_streaming(
+                PixelFormatEnum::RGB24,
+                SCREEN_WIDTH as u32,
+                SCREEN_HEIGHT as u32,
+            )
+            .map_err(|e| e.to_string())?;
+
         let mut event_pump = sdl_context.event_pump()?;
+        self.select_held_palette(&event_pump);
         canvas.clear();
         canvas.present();
+        let mut rumble = false;
+        let (mut steps, mut rumble_steps) = (0u32, 0u32);
+        let mut last_rumble = Instant::now();
+        let mut next_frame = Instant::now();
         loop {
-            if !self.process_events(&mut event_pump) {
+            if !self.process_events(&mut event_pump, &game_controller, &mut controllers) {
                 break Ok(());
             }
             self.cpu.step(&mut self.bus);
+
+            if self.bus.cartridge_mut().take_dirty() {
+                self.last_write = Some(Instant::now());
+            }
+            if self
+                .last_write
+                .is_some_and(|t| t.elapsed() >= SAVE_DEBOUNCE)
+            {
+                self.flush_save().map_err(|e| e.to_string())?;
+            }
+
+            // The motor state is averaged over the interval
+            steps += 1;
+            rumble_steps += self.rumble() as u32;
+            if last_rumble.elapsed() >= RUMBLE_INTERVAL {
+                last_rumble = Instant::now();
+                let strength = (rumble_steps as u64 * u16::MAX as u64 / steps as u64) as u16;
+                (steps, rumble_steps) = (0, 0);
+                match controllers.iter().any(Option::is_some) {
+                    true => {
+                        // Not every controller has a motor
+                        for controller in controllers.iter_mut().flatten() {
+                            let _ = controller.set_rumble(strength, strength, RUMBLE_DURATION_MS);
+                        }
+                    }
+                    false if (strength > 0) != rumble => {
+                        rumble = strength > 0;
+                        let title = match rumble {
+                            true => "GbOxyde [RUMBLE]",
+                            false => "GbOxyde",
+                        };
+                        canvas
+                            .window_mut()
+                            .set_title(title)
+                            .map_err(|e| e.to_string())?;
+                    }
+                    false => {}
+                }
+            }
+
+            if self.bus.take_frame() {
+                match self.sgb().is_some() && self.show_border {
+                    true => self.draw_border(&mut canvas, &mut border)?,
+                    false => self.draw_screen(&mut canvas, &mut screen)?,
+                }
+
+                // Keep the emulation at the hardware frame rate
+                next_frame += FRAME_DURATION;
+                let now = Instant::now();
+                match next_frame > now {
+                    true => std::thread::sleep(next_frame - now),
+                    false => next_frame = now,
+                }
+            }
+        }
+    }
+
+    /// Draws the last frame over the whole window
+    fn draw_screen(
+        &self,
+        canvas: &mut Canvas<Window>,
+        texture: &mut Texture,
+    ) -> Result<(), String> {
+        let pixels: Vec<u8> = self
+            .screen()
+            .iter()
+            .flat_map(|&color| self.color_correction.rgb(color))
+            .collect();
+        texture
+            .update(None, &pixels, SCREEN_WIDTH * 3)
+            .map_err(|e| e.to_string())?;
+        canvas.clear();
+        canvas.copy(texture, None, None)?;
+        canvas.present();
+        Ok(())
+    }
+
+    /// Draws the SGB border with the last frame inside it
+    fn draw_border(
+        &self,
+        canvas: &mut Canvas<Window>,
+        texture: &mut Texture,
+    ) -> Result<(), String> {
+        canvas.clear();
+        if let Some(sgb) = self.sgb() {
+            let pixels: Vec<u8> = sgb
+                .render_border(self.screen())
+                .into_iter()
+                .flat_map(|color| self.color_correction.rgb(color))
+                .collect();
+            texture
+                .update(None, &pixels, BORDER_WIDTH * 3)
+                .map_err(|e| e.to_string())?;
+            canvas.copy(texture, None, None)?;
         }
+        canvas.present();
+        Ok(())
     }
 
-    fn process_events(&mut self, event_pump: &mut EventPump) -> bool {
+    /// Picks a DMG palette from a direction and A or B held at startup
+    fn select_held_palette(&mut self, event_pump: &EventPump) {
+        let held: Vec<GbButton> = event_pump
+            .keyboard_state()
+            .pressed_scancodes()
+            .filter_map(Keycode::from_scancode)
+            .filter_map(map_key)
+            .collect();
+
+        let direction = held.iter().copied().find(|b| {
+            matches!(
+                b,
+                GbButton::Up | GbButton::Down | GbButton::Left | GbButton::Right
+            )
+        });
+        let modifier = held
+            .iter()
+            .copied()
+            .find(|b| matches!(b, GbButton::A | GbButton::B));
+
+        let palette = direction.and_then(|d| CompatPalette::from_buttons(d, modifier));
+        if let Some(palette) = palette {
+            if self.set_compat_palette(&palette) {
+                println!("Palette: {}", palette.name);
+            }
+        }
+    }
+
+    fn report_toggle_cheat(&mut self, index: usize) {
+        if let Some(enabled) = self.toggle_cheat(index) {
+            let state = match enabled {
+                true => "enabled",
+                false => "disabled",
+            };
+            println!("Cheat {} {}", self.cheats()[index].code, state);
+        }
+    }
+
+    /// Game controllers take the first free player slot when connected, the
+    /// keyboard always plays as player 0
+    fn process_events(
+        &mut self,
+        event_pump: &mut EventPump,
+        game_controller: &GameControllerSubsystem,
+        controllers: &mut [Option<GameController>; MAX_PLAYERS],
+    ) -> bool {
         for event in event_pump.poll_iter() {
             match event {
+                Event::ControllerDeviceAdded { which, .. } => {
+                    let slot = controllers.iter().position(Option::is_none);
+                    if let (Some(player), Ok(controller)) = (slot, game_controller.open(which)) {
+                        println!("Player {}: {}", player + 1, controller.name());
+                        controllers[player] = Some(controller);
+                    }
+                }
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    if let Some(player) = find_player(controllers, which) {
+                        controllers[player] = None;
+                        for button in GbButton::ALL {
+                            self.keyup(player, button);
+                        }
+                    }
+                }
+                Event::ControllerButtonDown { which, button, .. } => {
+                    if let (Some(player), Some(button)) =
+                        (find_player(controllers, which), map_button(button))
+                    {
+                        self.keydown(player, button);
+                    }
+                }
+                Event::ControllerButtonUp { which, button, .. } => {
+                    if let (Some(player), Some(button)) =
+                        (find_player(controllers, which), map_button(button))
+                    {
+                        self.keyup(player, button);
+                    }
+                }
                 Event::Quit { .. }
                 | Event::KeyDown {
                     keycode: Some(Keycode::Escape),
                     ..
                 } => return false,
+                // Tilt follows the mouse, the centre of the window is level
+                Event::MouseMotion { x, y, .. } if self.bus.has_accelerometer() => {
+                    let half_w = WINDOW_WIDTH as f32 / 2.0;
+                    let half_h = WINDOW_HEIGHT as f32 / 2.0;
+                    self.set_tilt((x as f32 - half_w) / half_w, (y as f32 - half_h) / half_h);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F2),
+                    repeat: false,
+                    ..
+                } => {
+                    let modes = ColorCorrection::ALL;
+                    let current = modes.iter().position(|&m| m == self.color_correction);
+                    let next = modes[current.map_or(0, |i| (i + 1) % modes.len())];
+                    self.set_color_correction(next);
+                    println!("Color correction: {}", next.name());
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F3),
+                    repeat: false,
+                    ..
+                } => self.show_border = !self.show_border,
                 Event::KeyDown {
                     keycode: Some(key),
                     repeat: false,
@@ -61,7 +490,9 @@ impl Gameboy {
                 } => {
                     if let Some(button) = map_key(key) {
                         println!("Pressed {:?}", button);
-                        self.bus.keydown(button);
+                        self.keydown(0, button);
+                    } else if let Some(index) = map_cheat(key) {
+                        self.report_toggle_cheat(index);
                     }
                 }
                 Event::KeyUp {
@@ -71,7 +502,7 @@ impl Gameboy {
                 } => {
                     if let Some(button) = map_key(key) {
                         println!("Released {:?}", button);
-                        self.bus.keyup(button);
+                        self.keyup(0, button);
                     }
                 }
                 _ => {}
@@ -82,6 +513,15 @@ impl Gameboy {
     }
 }
 
+impl Drop for Gameboy {
+    /// Keeps unsaved progress when the emulation stops on a panic
+    fn drop(&mut self) {
+        if let Err(e) = self.flush_save() {
+            eprintln!("Could not write the save file: {}", e);
+        }
+    }
+}
+
 fn map_key(key: Keycode) -> Option<GbButton> {
     match key {
         Keycode::A => Some(GbButton::A),
@@ -95,3 +535,39 @@ fn map_key(key: Keycode) -> Option<GbButton> {
         _ => None,
     }
 }
+
+fn map_button(button: Button) -> Option<GbButton> {
+    match button {
+        Button::A => Some(GbButton::A),
+        Button::B => Some(GbButton::B),
+        Button::Start => Some(GbButton::Start),
+        Button::Back => Some(GbButton::Select),
+        Button::DPadRight => Some(GbButton::Right),
+        Button::DPadLeft => Some(GbButton::Left),
+        Button::DPadUp => Some(GbButton::Up),
+        Button::DPadDown => Some(GbButton::Down),
+        _ => None,
+    }
+}
+
+/// Player index of the controller with joystick instance `id`
+fn find_player(controllers: &[Option<GameController>], id: u32) -> Option<usize> {
+    controllers
+        .iter()
+        .position(|c| c.as_ref().is_some_and(|c| c.instance_id() == id))
+}
+
+fn map_cheat(key: Keycode) -> Option<usize> {
+    match key {
+        Keycode::Num1 => Some(0),
+        Keycode::Num2 => Some(1),
+        Keycode::Num3 => Some(2),
+        Keycode::Num4 => Some(3),
+        Keycode::Num5 => Some(4),
+        Keycode::Num6 => Some(5),
+        Keycode::Num7 => Some(6),
+        Keycode::Num8 => Some(7),
+        Keycode::Num9 => Some(8),
+        _ => None,
+    }
+}
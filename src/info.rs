@@ -0,0 +1,203 @@
+use std::fmt::Write;
+
+use crate::bus::cartridge::header_base;
+use crate::header::{
+    global_checksum, has_logo, header_checksum, result, stored_checksums, Header, HeaderError,
+    HEADER_LOC, HEADER_SIZE,
+};
+
+const PRINT_WIDTH: usize = 12;
+
+/// Header and integrity report of a ROM image, computed without running it.
+/// Checksums and logo are checked from the raw bytes, so they are reported
+/// even when the header is unusable for running the ROM.
+pub struct RomInfo {
+    pub header: Result<Header, HeaderError>,
+    pub file_size: usize,
+    pub stored_checksum: u8,
+    pub computed_checksum: u8,
+    pub stored_global_checksum: u16,
+    pub computed_global_checksum: u16,
+    pub logo: bool,
+    pub crc32: u32,
+    pub sha1: String,
+}
+
+impl RomInfo {
+    /// Only fails when `rom` is too short to contain a header
+    pub fn new(rom: &[u8]) -> Result<Self, HeaderError> {
+        if rom.len() < HEADER_LOC + HEADER_SIZE {
+            return Err(HeaderError::RomTooShort(rom.len()));
+        }
+        // Same header as the emulator uses, the menu's one for MMM01
+        let base = header_base(rom);
+        let bank = &rom[base..];
+        let (stored_checksum, stored_global_checksum) = stored_checksums(bank);
+
+        Ok(Self {
+            header: Header::parse_at(rom, base),
+            file_size: rom.len(),
+            stored_checksum,
+            computed_checksum: header_checksum(bank),
+            stored_global_checksum,
+            computed_global_checksum: global_checksum(rom),
+            logo: has_logo(bank),
+            crc32: crc32fast::hash(rom),
+            sha1: sha1_smol::Sha1::from(rom).digest().to_string(),
+        })
+    }
+
+    pub fn header_checksum_ok(&self) -> bool {
+        self.stored_checksum == self.computed_checksum
+    }
+
+    pub fn global_checksum_ok(&self) -> bool {
+        self.stored_global_checksum == self.computed_global_checksum
+    }
+
+    pub fn print(&self) {
+        let width = PRINT_WIDTH;
+
+        match &self.header {
+            Ok(header) => header.print(width),
+            Err(e) => {
+                println!("{:<width$} {}", "Header:", e, width = width);
+                println!(
+                    "{:<width$} {}",
+                    "Checksum:",
+                    result(self.header_checksum_ok()),
+                    width = width
+                );
+                println!(
+                    "{:<width$} {}",
+                    "Global Sum:",
+                    result(self.global_checksum_ok()),
+                    width = width
+                );
+                println!("{:<width$} {}", "Logo:", result(self.logo), width = width);
+            }
+        }
+        println!(
+            "{:<width$} header ${:02X}/${:02X}, global ${:04X}/${:04X} (stored/computed)",
+            "Checksums:",
+            self.stored_checksum,
+            self.computed_checksum,
+            self.stored_global_checksum,
+            self.computed_global_checksum,
+            width = width
+        );
+        if let Ok(header) = &self.header {
+            let cart_type = header.cart_type;
+            println!(
+                "{:<width$} {} (code ${:02X})",
+                "Mapper:",
+                cart_type.mapper(),
+                cart_type.code(),
+                width = width
+            );
+            println!(
+                "{:<width$} battery: {}, rtc: {}, rumble: {}",
+                "Features:",
+                yes_no(cart_type.has_battery()),
+                yes_no(cart_type.has_rtc()),
+                yes_no(cart_type.has_rumble()),
+                width = width
+            );
+        }
+        println!(
+            "{:<width$} {} bytes ({} banks)",
+            "File Size:",
+            self.file_size,
+            self.file_size / 0x4000,
+            width = width
+        );
+        println!("{:<width$} {:08x}", "CRC32:", self.crc32, width = width);
+        println!("{:<width$} {}", "SHA-1:", self.sha1, width = width);
+    }
+
+    /// Single-line JSON object for the ROM at `path`, suitable for JSON Lines
+    /// output. Header fields are replaced by `header_error` when the header
+    /// could not be parsed.
+    pub fn to_json(&self, path: &str) -> String {
+        let mut s = String::new();
+        s.push('{');
+        let _ = write!(s, "\"path\":{},", json_string(path));
+        match &self.header {
+            Ok(header) => {
+                let cart_type = header.cart_type;
+                let _ = write!(s, "\"title\":{},", json_string(&header.title));
+                let _ = write!(s, "\"licensee\":{},", json_string(header.licensee));
+                let _ = write!(s, "\"type\":{},", json_string(cart_type.name()));
+                let _ = write!(s, "\"type_code\":{},", cart_type.code());
+                let _ = write!(s, "\"mapper\":{},", json_string(cart_type.mapper()));
+                let _ = write!(s, "\"battery\":{},", cart_type.has_battery());
+                let _ = write!(s, "\"rtc\":{},", cart_type.has_rtc());
+                let _ = write!(s, "\"rumble\":{},", cart_type.has_rumble());
+                let _ = write!(s, "\"cgb\":{},", json_string(&header.cgb.to_string()));
+                let _ = write!(s, "\"sgb\":{},", json_string(&header.sgb.to_string()));
+                let _ = write!(s, "\"rom_size\":{},", header.rom_size.bytes());
+                let _ = write!(s, "\"rom_banks\":{},", header.rom_size.banks());
+                let _ = write!(s, "\"ram_size\":{},", header.ram_size.bytes());
+                let _ = write!(s, "\"ram_banks\":{},", header.ram_size.banks());
+                let _ = write!(s, "\"region\":{},", json_string(header.dst));
+                let _ = write!(s, "\"version\":{},", header.version);
+            }
+            Err(e) => {
+                let _ = write!(s, "\"header_error\":{},", json_string(&e.to_string()));
+            }
+        }
+        let _ = write!(s, "\"header_checksum\":{},", self.stored_checksum);
+        let _ = write!(
+            s,
+            "\"header_checksum_computed\":{},",
+            self.computed_checksum
+        );
+        let _ = write!(s, "\"header_checksum_ok\":{},", self.header_checksum_ok());
+        let _ = write!(s, "\"global_checksum\":{},", self.stored_global_checksum);
+        let _ = write!(
+            s,
+            "\"global_checksum_computed\":{},",
+            self.computed_global_checksum
+        );
+        let _ = write!(s, "\"global_checksum_ok\":{},", self.global_checksum_ok());
+        let _ = write!(s, "\"logo_ok\":{},", self.logo);
+        let _ = write!(s, "\"file_size\":{},", self.file_size);
+        let _ = write!(s, "\"crc32\":\"{:08x}\",", self.crc32);
+        let _ = write!(s, "\"sha1\":\"{}\"", self.sha1);
+        s.push('}');
+        s
+    }
+}
+
+/// JSON Lines record for a ROM at `path` that could not be read at all
+pub fn json_error(path: &str, error: &str) -> String {
+    format!(
+        "{{\"path\":{},\"error\":{}}}",
+        json_string(path),
+        json_string(error)
+    )
+}
+
+const fn yes_no(value: bool) -> &'static str {
+    match value {
+        true => "yes",
+        false => "no",
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut s = String::with_capacity(value.len() + 2);
+    s.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(s, "\\u{:04x}", c as u32);
+            }
+            c => s.push(c),
+        }
+    }
+    s.push('"');
+    s
+}
@@ -1,4 +1,11 @@
+pub mod color;
 pub mod gameboy;
+pub mod info;
+pub mod model;
+pub mod patch;
+pub mod rom;
 
 mod bus;
 mod cpu;
+
+pub use bus::{boot, cartridge::header, cheats, compat, joypad, ppu, search, sgb, video};
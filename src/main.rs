@@ -1,13 +1,194 @@
-use std::{fs::File, io::Read};
+use std::{fs, path::Path};
 
-use anyhow::Result;
-use gboxyde::gameboy::Gameboy;
+use anyhow::{bail, Result};
+use gboxyde::{
+    color::ColorCorrection,
+    compat::CompatPalette,
+    gameboy::Gameboy,
+    header::fix_header,
+    info::{json_error, RomInfo},
+    model::Model,
+    patch, rom,
+};
+
+const USAGE: &str = "Usage: gboxyde [--patch <file>] [--boot-rom <file>]
+               [--model dmg0|dmg|mgb|sgb|sgb2|cgb|agb]
+               [--color-correction raw|gbc|gba] [--palette <up|left|down|right>[+a|+b]]
+               [--no-border] <rom>
+       gboxyde info [--json] <rom>...
+       gboxyde fix-header [--pad] <rom> [output]";
 
 fn main() -> Result<()> {
-    let romfile = std::env::args().nth(1).expect("Missing argument");
-    let mut file = File::open(romfile)?;
-    let mut rom = vec![];
-    file.read_to_end(&mut rom)?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("info") => info(&args[1..]),
+        Some("fix-header") => fix(&args[1..]),
+        Some(_) => run(&args),
+        None => bail!(USAGE),
+    }
+}
+
+fn read_rom(path: &str) -> Result<Vec<u8>> {
+    Ok(rom::load(path)?)
+}
+
+struct RunOptions {
+    romfile: String,
+    patch: Option<String>,
+    boot_rom: Option<String>,
+    model: Option<Model>,
+    color_correction: ColorCorrection,
+    palette: Option<CompatPalette>,
+    border: bool,
+}
+
+fn parse_run_args(args: &[String]) -> Result<RunOptions> {
+    let mut romfile = None;
+    let mut patch = None;
+    let mut boot_rom = None;
+    let mut model = None;
+    let mut color_correction = ColorCorrection::default();
+    let mut palette = None;
+    let mut border = true;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--patch" => match args.next() {
+                Some(path) => patch = Some(path.clone()),
+                None => bail!(USAGE),
+            },
+            "--boot-rom" => match args.next() {
+                Some(path) => boot_rom = Some(path.clone()),
+                None => bail!(USAGE),
+            },
+            "--model" => match args.next() {
+                Some(name) => match Model::from_name(name) {
+                    Some(m) => model = Some(m),
+                    None => bail!("Unknown model \"{}\"\n{}", name, USAGE),
+                },
+                None => bail!(USAGE),
+            },
+            "--color-correction" => match args.next() {
+                Some(name) => match ColorCorrection::from_name(name) {
+                    Some(mode) => color_correction = mode,
+                    None => bail!("Unknown color correction \"{}\"\n{}", name, USAGE),
+                },
+                None => bail!(USAGE),
+            },
+            "--palette" => match args.next() {
+                Some(name) => match CompatPalette::from_name(name) {
+                    Some(p) => palette = Some(p),
+                    None => bail!("Unknown palette \"{}\"\n{}", name, USAGE),
+                },
+                None => bail!(USAGE),
+            },
+            "--no-border" => border = false,
+            _ if romfile.is_none() => romfile = Some(arg.clone()),
+            _ => bail!(USAGE),
+        }
+    }
+
+    match romfile {
+        Some(romfile) => Ok(RunOptions {
+            romfile,
+            patch,
+            boot_rom,
+            model,
+            color_correction,
+            palette,
+            border,
+        }),
+        None => bail!(USAGE),
+    }
+}
+
+fn run(args: &[String]) -> Result<()> {
+    let options = parse_run_args(args)?;
+    let romfile = options.romfile.as_str();
+    let mut rom = read_rom(romfile)?;
+
+    let patch_path = options
+        .patch
+        .map(Into::into)
+        .or_else(|| patch::find(romfile));
+    if let Some(path) = patch_path {
+        println!("Applying patch {}", path.display());
+        rom = patch::apply(&rom, &fs::read(&path)?)?;
+    }
+
+    let mut gameboy = Gameboy::new(rom, options.model)?;
+    gameboy.set_color_correction(options.color_correction);
+    gameboy.set_show_border(options.border);
+    if let Some(palette) = &options.palette {
+        if !gameboy.set_compat_palette(palette) {
+            println!("Ignoring --palette, it only applies to DMG games on CGB");
+        }
+    }
+    if let Some(path) = &options.boot_rom {
+        gameboy.load_boot_rom(fs::read(path)?)?;
+    }
+    gameboy.load_save(Path::new(romfile).with_extension("sav"))?;
+
+    let cheats = Path::new(romfile).with_extension("cht");
+    if cheats.is_file() {
+        for (line, e) in gameboy.load_cheats(&cheats)? {
+            eprintln!("{}:{}: {}, skipped", cheats.display(), line, e);
+        }
+        println!("Loaded {} cheat(s)", gameboy.cheats().len());
+    }
+    gameboy.run().map_err(anyhow::Error::msg)
+}
+
+fn info(args: &[String]) -> Result<()> {
+    let json = args.iter().any(|a| a == "--json");
+    let roms: Vec<&String> = args.iter().filter(|a| *a != "--json").collect();
+    if roms.is_empty() {
+        bail!(USAGE);
+    }
+
+    let mut failed = 0;
+    for romfile in roms {
+        let info = read_rom(romfile).and_then(|rom| Ok(RomInfo::new(&rom)?));
+        match (info, json) {
+            (Ok(info), true) => println!("{}", info.to_json(romfile)),
+            (Ok(info), false) => {
+                let line = "-".repeat(30);
+                println!("{}", romfile);
+                println!("{}", &line);
+                info.print();
+                println!("{}", &line);
+            }
+            (Err(e), true) => {
+                println!("{}", json_error(romfile, &e.to_string()));
+                failed += 1;
+            }
+            (Err(e), false) => {
+                eprintln!("{}: {}", romfile, e);
+                failed += 1;
+            }
+        }
+    }
+
+    match failed {
+        0 => Ok(()),
+        n => bail!("{} ROM(s) could not be read", n),
+    }
+}
+
+fn fix(args: &[String]) -> Result<()> {
+    let pad = args.iter().any(|a| a == "--pad");
+    let paths: Vec<&String> = args.iter().filter(|a| *a != "--pad").collect();
+    let (input, output) = match paths.as_slice() {
+        [input] => (input, input),
+        [input, output] => (input, output),
+        _ => bail!(USAGE),
+    };
+
+    // Archives are not rewritten, only raw ROMs are accepted
+    let mut rom = fs::read(input)?;
+    fix_header(&mut rom, pad)?;
+    fs::write(output, &rom)?;
 
-    Gameboy::new(rom).run().map_err(anyhow::Error::msg)
+    Ok(())
 }
@@ -0,0 +1,334 @@
+use std::{error::Error, fmt, path::Path};
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+const UPS_MAGIC: &[u8] = b"UPS1";
+const BPS_MAGIC: &[u8] = b"BPS1";
+
+/// Source, target and patch CRC32s at the end of UPS and BPS patches
+const FOOTER_SIZE: usize = 12;
+
+/// Largest valid ROM, patches producing more are rejected before allocating
+const MAX_TARGET_SIZE: usize = 8 * 1024 * 1024;
+
+pub const PATCH_EXTENSIONS: [&str; 3] = ["ips", "ups", "bps"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    UnknownFormat,
+    Truncated,
+    OutOfBounds,
+    TooLarge(usize),
+    SourceSize { expected: usize, actual: usize },
+    SourceChecksum { expected: u32, actual: u32 },
+    TargetChecksum { expected: u32, actual: u32 },
+    PatchChecksum { expected: u32, actual: u32 },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatchError::UnknownFormat => write!(f, "Unknown patch format"),
+            PatchError::Truncated => write!(f, "Patch is truncated"),
+            PatchError::OutOfBounds => write!(f, "Patch accesses data out of bounds"),
+            PatchError::TooLarge(size) => write!(
+                f,
+                "Patched ROM would be {} bytes, larger than any valid ROM",
+                size
+            ),
+            PatchError::SourceSize { expected, actual } => write!(
+                f,
+                "ROM is {} bytes long, patch expects {} bytes",
+                actual, expected
+            ),
+            PatchError::SourceChecksum { expected, actual } => write!(
+                f,
+                "ROM CRC32 is {:08x}, patch expects {:08x}",
+                actual, expected
+            ),
+            PatchError::TargetChecksum { expected, actual } => write!(
+                f,
+                "Patched ROM CRC32 is {:08x}, patch expects {:08x}",
+                actual, expected
+            ),
+            PatchError::PatchChecksum { expected, actual } => write!(
+                f,
+                "Patch CRC32 is {:08x}, expected {:08x}",
+                actual, expected
+            ),
+        }
+    }
+}
+
+impl Error for PatchError {}
+
+/// Finds a `<rom>.ips`, `<rom>.ups` or `<rom>.bps` patch next to the ROM
+pub fn find(rom_path: impl AsRef<Path>) -> Option<std::path::PathBuf> {
+    PATCH_EXTENSIONS
+        .iter()
+        .map(|ext| rom_path.as_ref().with_extension(ext))
+        .find(|path| path.is_file())
+}
+
+/// Applies an IPS, UPS or BPS patch to `rom`, detected from its magic
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    if patch.starts_with(IPS_MAGIC) {
+        apply_ips(rom, patch)
+    } else if patch.starts_with(UPS_MAGIC) {
+        apply_ups(rom, patch)
+    } else if patch.starts_with(BPS_MAGIC) {
+        apply_bps(rom, patch)
+    } else {
+        Err(PatchError::UnknownFormat)
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    const fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], PatchError> {
+        let end = self.pos.checked_add(count).ok_or(PatchError::Truncated)?;
+        let bytes = self.data.get(self.pos..end).ok_or(PatchError::Truncated)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, PatchError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn be(&mut self, count: usize) -> Result<usize, PatchError> {
+        let bytes = self.bytes(count)?;
+        Ok(bytes.iter().fold(0, |acc, &b| (acc << 8) | b as usize))
+    }
+
+    /// Variable-length integer used by UPS and BPS
+    fn varint(&mut self) -> Result<usize, PatchError> {
+        let mut value = 0usize;
+        let mut shift = 1usize;
+        loop {
+            let x = self.byte()?;
+            value = value
+                .checked_add(
+                    (x as usize & 0x7F)
+                        .checked_mul(shift)
+                        .ok_or(PatchError::OutOfBounds)?,
+                )
+                .ok_or(PatchError::OutOfBounds)?;
+            if x & 0x80 != 0 {
+                break Ok(value);
+            }
+            shift = shift.checked_shl(7).ok_or(PatchError::OutOfBounds)?;
+            value = value.checked_add(shift).ok_or(PatchError::OutOfBounds)?;
+        }
+    }
+}
+
+fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut target = rom.to_vec();
+    let mut reader = Reader::new(patch, IPS_MAGIC.len());
+
+    loop {
+        if reader.bytes(3)? == IPS_EOF {
+            break;
+        }
+        reader.pos -= 3;
+
+        let offset = reader.be(3)?;
+        let (data, len) = match reader.be(2)? {
+            0 => {
+                let len = reader.be(2)?;
+                (None, len)
+            }
+            len => (Some(reader.bytes(len)?), len),
+        };
+
+        let end = target_end(offset, len)?;
+        if target.len() < end {
+            target.resize(end, 0);
+        }
+        match data {
+            Some(data) => target[offset..end].copy_from_slice(data),
+            None => {
+                let value = reader.byte()?;
+                target[offset..end].fill(value);
+            }
+        }
+    }
+
+    // Optional truncation extension
+    if let Ok(size) = reader.be(3) {
+        target.truncate(size);
+    }
+
+    Ok(target)
+}
+
+/// End of a `len` byte write at `offset`, rejecting sizes no ROM can have
+fn target_end(offset: usize, len: usize) -> Result<usize, PatchError> {
+    let end = offset.checked_add(len).ok_or(PatchError::OutOfBounds)?;
+    match end > MAX_TARGET_SIZE {
+        true => Err(PatchError::TooLarge(end)),
+        false => Ok(end),
+    }
+}
+
+/// Reads the source and target sizes shared by UPS and BPS headers
+fn read_sizes(rom: &[u8], reader: &mut Reader) -> Result<usize, PatchError> {
+    let source_size = reader.varint()?;
+    if source_size != rom.len() {
+        return Err(PatchError::SourceSize {
+            expected: source_size,
+            actual: rom.len(),
+        });
+    }
+
+    let target_size = reader.varint()?;
+    match target_size > MAX_TARGET_SIZE {
+        true => Err(PatchError::TooLarge(target_size)),
+        false => Ok(target_size),
+    }
+}
+
+fn check_footer(rom: &[u8], target: &[u8], patch: &[u8]) -> Result<(), PatchError> {
+    let footer = &patch[patch.len() - FOOTER_SIZE..];
+    let crc =
+        |i: usize| u32::from_le_bytes([footer[i], footer[i + 1], footer[i + 2], footer[i + 3]]);
+
+    let expected = crc(8);
+    let actual = crc32fast::hash(&patch[..patch.len() - 4]);
+    if expected != actual {
+        return Err(PatchError::PatchChecksum { expected, actual });
+    }
+
+    let expected = crc(0);
+    let actual = crc32fast::hash(rom);
+    if expected != actual {
+        return Err(PatchError::SourceChecksum { expected, actual });
+    }
+
+    let expected = crc(4);
+    let actual = crc32fast::hash(target);
+    if expected != actual {
+        return Err(PatchError::TargetChecksum { expected, actual });
+    }
+
+    Ok(())
+}
+
+fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    if patch.len() < UPS_MAGIC.len() + FOOTER_SIZE {
+        return Err(PatchError::Truncated);
+    }
+
+    let end = patch.len() - FOOTER_SIZE;
+    let mut reader = Reader::new(&patch[..end], UPS_MAGIC.len());
+    let target_size = read_sizes(rom, &mut reader)?;
+
+    let mut target = rom.to_vec();
+    target.resize(target_size, 0);
+
+    let mut pos = 0usize;
+    while reader.pos < end {
+        pos = pos
+            .checked_add(reader.varint()?)
+            .ok_or(PatchError::OutOfBounds)?;
+        loop {
+            let x = reader.byte()?;
+            if x == 0 {
+                pos = pos.saturating_add(1);
+                break;
+            }
+            if let Some(b) = target.get_mut(pos) {
+                *b ^= x;
+            }
+            pos = pos.saturating_add(1);
+        }
+    }
+
+    check_footer(rom, &target, patch)?;
+    Ok(target)
+}
+
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    if patch.len() < BPS_MAGIC.len() + FOOTER_SIZE {
+        return Err(PatchError::Truncated);
+    }
+
+    let end = patch.len() - FOOTER_SIZE;
+    let mut reader = Reader::new(&patch[..end], BPS_MAGIC.len());
+    let target_size = read_sizes(rom, &mut reader)?;
+    let metadata_size = reader.varint()?;
+    reader.bytes(metadata_size)?;
+
+    let mut target = Vec::with_capacity(target_size);
+    let mut source_rel = 0isize;
+    let mut target_rel = 0isize;
+
+    while reader.pos < end {
+        let data = reader.varint()?;
+        let len = (data >> 2) + 1;
+        if target_end(target.len(), len)? > target_size {
+            return Err(PatchError::OutOfBounds);
+        }
+        match data & 0b11 {
+            // SourceRead
+            0 => {
+                let start = target.len();
+                target.extend_from_slice(source_slice(rom, start, len)?);
+            }
+            // TargetRead
+            1 => target.extend_from_slice(reader.bytes(len)?),
+            // SourceCopy
+            2 => {
+                source_rel = relative(source_rel, reader.varint()?)?;
+                let start = usize::try_from(source_rel).map_err(|_| PatchError::OutOfBounds)?;
+                target.extend_from_slice(source_slice(rom, start, len)?);
+                source_rel = start as isize + len as isize;
+            }
+            // TargetCopy, may overlap with the bytes being written
+            _ => {
+                target_rel = relative(target_rel, reader.varint()?)?;
+                for _ in 0..len {
+                    let i = usize::try_from(target_rel).map_err(|_| PatchError::OutOfBounds)?;
+                    let b = *target.get(i).ok_or(PatchError::OutOfBounds)?;
+                    target.push(b);
+                    target_rel += 1;
+                }
+            }
+        }
+    }
+
+    if target.len() != target_size {
+        return Err(PatchError::OutOfBounds);
+    }
+
+    check_footer(rom, &target, patch)?;
+    Ok(target)
+}
+
+fn source_slice(rom: &[u8], start: usize, len: usize) -> Result<&[u8], PatchError> {
+    let end = start.checked_add(len).ok_or(PatchError::OutOfBounds)?;
+    rom.get(start..end).ok_or(PatchError::OutOfBounds)
+}
+
+/// Applies a signed BPS offset to a relative copy position
+fn relative(pos: isize, offset: usize) -> Result<isize, PatchError> {
+    pos.checked_add(signed(offset))
+        .ok_or(PatchError::OutOfBounds)
+}
+
+const fn signed(value: usize) -> isize {
+    let magnitude = (value >> 1) as isize;
+    match value & 1 {
+        0 => magnitude,
+        _ => -magnitude,
+    }
+}
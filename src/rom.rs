@@ -0,0 +1,61 @@
+use std::{
+    fs,
+    io::{self, Cursor, Read},
+    path::Path,
+};
+
+use flate2::read::GzDecoder;
+use zip::ZipArchive;
+
+const ZIP_MAGIC: [u8; 4] = [0x50, 0x4B, 0x03, 0x04];
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+const ROM_EXTENSIONS: [&str; 2] = ["gb", "gbc"];
+
+/// Reads a ROM file, transparently extracting it from a zip or gzip container
+pub fn load(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    extract(fs::read(path)?)
+}
+
+/// Returns `data` unchanged unless it is a zip or gzip container, in which
+/// case the ROM is extracted. For zips, the first .gb/.gbc entry is used.
+pub fn extract(data: Vec<u8>) -> io::Result<Vec<u8>> {
+    if data.starts_with(&ZIP_MAGIC) {
+        extract_zip(data)
+    } else if data.starts_with(&GZIP_MAGIC) {
+        let mut rom = vec![];
+        GzDecoder::new(data.as_slice()).read_to_end(&mut rom)?;
+        Ok(rom)
+    } else {
+        Ok(data)
+    }
+}
+
+fn extract_zip(data: Vec<u8>) -> io::Result<Vec<u8>> {
+    let mut archive = ZipArchive::new(Cursor::new(data))?;
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if file.is_file() && is_rom_name(file.name()) {
+            let mut rom = Vec::with_capacity(file.size() as usize);
+            file.read_to_end(&mut rom)?;
+            return Ok(rom);
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "No .gb/.gbc file in archive",
+    ))
+}
+
+fn is_rom_name(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            ROM_EXTENSIONS
+                .iter()
+                .any(|rom_ext| ext.eq_ignore_ascii_case(rom_ext))
+        })
+}